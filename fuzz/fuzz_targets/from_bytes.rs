@@ -0,0 +1,16 @@
+//! Feeds arbitrary bytes to the transaction parsers. They must return `Ok` or
+//! `Err` for every input and never panic.
+//!
+//! Run with `cargo +nightly fuzz run from_bytes fuzz/corpus/from_bytes`.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rust_week_3_exercises::BitcoinTransaction;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok((tx, consumed)) = BitcoinTransaction::from_bytes(data) {
+        assert!(consumed <= data.len());
+        let _ = tx.to_bytes();
+    }
+    let _ = BitcoinTransaction::from_bytes_inputs_only(data);
+});
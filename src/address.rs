@@ -0,0 +1,152 @@
+use crate::hashes::double_sha256;
+use crate::script::ScriptType;
+use crate::{Script, TransactionOutput};
+use serde::{Deserialize, Serialize};
+
+/// The chain an address is encoded for. Signet shares testnet's prefixes.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub enum Network {
+    Bitcoin,
+    Testnet,
+    Signet,
+    Regtest,
+}
+
+impl Network {
+    fn p2pkh_prefix(self) -> u8 {
+        match self {
+            Network::Bitcoin => 0x00,
+            _ => 0x6f,
+        }
+    }
+
+    fn p2sh_prefix(self) -> u8 {
+        match self {
+            Network::Bitcoin => 0x05,
+            _ => 0xc4,
+        }
+    }
+
+    fn bech32_hrp(self) -> &'static str {
+        match self {
+            Network::Bitcoin => "bc",
+            Network::Testnet | Network::Signet => "tb",
+            Network::Regtest => "bcrt",
+        }
+    }
+}
+
+const BASE58_ALPHABET: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+fn base58check(prefix: u8, payload: &[u8]) -> String {
+    let mut data = vec![prefix];
+    data.extend_from_slice(payload);
+    let checksum = double_sha256(&data);
+    data.extend_from_slice(&checksum[..4]);
+
+    // Repeated division of the big-endian number by 58, least significant
+    // digit first.
+    let mut digits: Vec<u8> = Vec::new();
+    for &byte in &data {
+        let mut carry = byte as u32;
+        for digit in digits.iter_mut() {
+            carry += (*digit as u32) << 8;
+            *digit = (carry % 58) as u8;
+            carry /= 58;
+        }
+        while carry > 0 {
+            digits.push((carry % 58) as u8);
+            carry /= 58;
+        }
+    }
+    let leading_zeros = data.iter().take_while(|&&b| b == 0).count();
+    std::iter::repeat_n(b'1', leading_zeros)
+        .chain(digits.iter().rev().map(|&d| BASE58_ALPHABET[d as usize]))
+        .map(char::from)
+        .collect()
+}
+
+const BECH32_CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+const BECH32_CONST: u32 = 1;
+const BECH32M_CONST: u32 = 0x2bc8_30a3;
+
+fn bech32_polymod(values: &[u8]) -> u32 {
+    const GENERATOR: [u32; 5] = [
+        0x3b6a_57b2,
+        0x2650_8e6d,
+        0x1ea1_19fa,
+        0x3d42_33dd,
+        0x2a14_62b3,
+    ];
+    let mut chk = 1u32;
+    for &value in values {
+        let top = chk >> 25;
+        chk = ((chk & 0x01ff_ffff) << 5) ^ value as u32;
+        for (i, generator) in GENERATOR.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                chk ^= generator;
+            }
+        }
+    }
+    chk
+}
+
+/// BIP173 segwit v0 addresses use bech32; BIP350 v1 and later use bech32m.
+fn segwit_address(hrp: &str, version: u8, program: &[u8]) -> String {
+    let mut data = vec![version];
+    let mut acc = 0u32;
+    let mut bits = 0;
+    for &byte in program {
+        acc = (acc << 8) | byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            data.push(((acc >> bits) & 0x1f) as u8);
+        }
+    }
+    if bits > 0 {
+        data.push(((acc << (5 - bits)) & 0x1f) as u8);
+    }
+
+    let mut values: Vec<u8> = hrp.bytes().map(|b| b >> 5).collect();
+    values.push(0);
+    values.extend(hrp.bytes().map(|b| b & 0x1f));
+    values.extend_from_slice(&data);
+    values.extend_from_slice(&[0; 6]);
+    let constant = if version == 0 {
+        BECH32_CONST
+    } else {
+        BECH32M_CONST
+    };
+    let checksum = bech32_polymod(&values) ^ constant;
+    data.extend((0..6).map(|i| ((checksum >> (5 * (5 - i))) & 0x1f) as u8));
+
+    let mut address = String::from(hrp);
+    address.push('1');
+    address.extend(data.iter().map(|&d| BECH32_CHARSET[d as usize] as char));
+    address
+}
+
+impl Script {
+    /// Encodes the script as an address on `network`: base58check for P2PKH
+    /// and P2SH, bech32 or bech32m for witness programs. Scripts without an
+    /// address form, such as P2PK or `OP_RETURN`, return `None`, as do v0
+    /// programs other than 20 or 32 bytes, which BIP141 makes unspendable.
+    pub fn to_address(&self, network: Network) -> Option<String> {
+        let b = &self.bytes;
+        match self.script_type() {
+            ScriptType::P2pkh => Some(base58check(network.p2pkh_prefix(), &b[3..23])),
+            ScriptType::P2sh => Some(base58check(network.p2sh_prefix(), &b[2..22])),
+            _ => match self.witness_program()? {
+                (0, program) if !matches!(program.len(), 20 | 32) => None,
+                (version, program) => Some(segwit_address(network.bech32_hrp(), version, &program)),
+            },
+        }
+    }
+}
+
+impl TransactionOutput {
+    pub fn address(&self, network: Network) -> Option<String> {
+        self.script_pubkey.to_address(network)
+    }
+}
@@ -0,0 +1,112 @@
+use crate::BitcoinError;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// An amount of bitcoin, counted in satoshis.
+#[derive(
+    Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Default, Hash, Serialize, Deserialize,
+)]
+pub struct Amount(pub u64);
+
+impl Amount {
+    pub const ZERO: Amount = Amount(0);
+    pub const ONE_SAT: Amount = Amount(1);
+    pub const ONE_BTC: Amount = Amount(100_000_000);
+    /// The 21 million BTC supply cap.
+    pub const MAX_MONEY: Amount = Amount(21_000_000 * 100_000_000);
+
+    pub fn from_sat(sat: u64) -> Self {
+        Amount(sat)
+    }
+
+    pub fn to_sat(self) -> u64 {
+        self.0
+    }
+
+    /// Converts a BTC amount given as a float, rounding to the nearest
+    /// satoshi with halves rounded away from zero. NaN, negative values (even
+    /// ones that would round to zero) and amounts above `MAX_MONEY` are
+    /// rejected with `InvalidFormat`.
+    pub fn try_from_btc(btc: f64) -> Result<Amount, BitcoinError> {
+        if btc < 0.0 {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        let sat = (btc * Self::ONE_BTC.0 as f64).round();
+        if !(0.0..=Self::MAX_MONEY.0 as f64).contains(&sat) {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        Ok(Amount(sat as u64))
+    }
+
+    pub fn checked_add(self, other: Amount) -> Option<Amount> {
+        self.0.checked_add(other.0).map(Amount)
+    }
+
+    pub fn checked_sub(self, other: Amount) -> Option<Amount> {
+        self.0.checked_sub(other.0).map(Amount)
+    }
+}
+
+impl fmt::Display for Amount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} sat", self.0)
+    }
+}
+
+/// A fee rate in satoshis per 1000 virtual bytes, the unit Bitcoin Core uses
+/// for its relay settings.
+#[derive(
+    Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Default, Hash, Serialize, Deserialize,
+)]
+pub struct FeeRate(pub u64);
+
+impl FeeRate {
+    /// Bitcoin Core's default `-minrelaytxfee`.
+    pub const DEFAULT_MIN_RELAY: FeeRate = FeeRate(1_000);
+
+    pub fn from_sat_per_kvb(sat: u64) -> Self {
+        FeeRate(sat)
+    }
+
+    /// Saturates at `u64::MAX` sat/kvB rather than overflowing.
+    pub fn from_sat_per_vb(sat: u64) -> Self {
+        FeeRate(sat.saturating_mul(1_000))
+    }
+
+    /// The fee for a transaction of `weight` weight units at this rate,
+    /// rounded up to the next satoshi. A product too large for a `u64`
+    /// saturates to `u64::MAX` satoshis, far above `MAX_MONEY`, so it can
+    /// never be mistaken for an affordable fee.
+    pub fn fee_for(self, weight: usize) -> Amount {
+        Amount(
+            self.0
+                .checked_mul(weight as u64)
+                .map_or(u64::MAX, |product| product.div_ceil(4_000)),
+        )
+    }
+
+    /// The fee for `vsize` virtual bytes at this rate, rounded up and
+    /// saturating like `fee_for`.
+    pub fn fee_for_vsize(self, vsize: usize) -> Amount {
+        Amount(
+            self.0
+                .checked_mul(vsize as u64)
+                .map_or(u64::MAX, |product| product.div_ceil(1_000)),
+        )
+    }
+}
+
+impl fmt::Display for FeeRate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} sat/kvB", self.0)
+    }
+}
+
+/// What a UTXO contributes to a transaction once the fee for spending it is
+/// paid: `utxo_value` minus the fee for `input_vsize` vbytes at `fee_rate`.
+/// Negative when the input costs more to spend than it is worth.
+pub fn effective_value(utxo_value: Amount, input_vsize: usize, fee_rate: FeeRate) -> i64 {
+    let fee = fee_rate.fee_for_vsize(input_vsize).0;
+    let value = i64::try_from(utxo_value.0).unwrap_or(i64::MAX);
+    value.saturating_sub(i64::try_from(fee).unwrap_or(i64::MAX))
+}
@@ -0,0 +1,362 @@
+use crate::hashes::double_sha256;
+use crate::{BitcoinError, BitcoinTransaction, CompactSize, Txid, advance, compute_txids, rest};
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::path::Path;
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct BlockHeader {
+    pub version: u32,
+    pub prev_blockhash: [u8; 32],
+    pub merkle_root: [u8; 32],
+    pub time: u32,
+    pub bits: u32,
+    pub nonce: u32,
+}
+
+impl BlockHeader {
+    pub const SIZE: usize = 80;
+
+    pub fn new(
+        version: u32,
+        prev_blockhash: [u8; 32],
+        merkle_root: [u8; 32],
+        time: u32,
+        bits: u32,
+        nonce: u32,
+    ) -> Self {
+        BlockHeader {
+            version,
+            prev_blockhash,
+            merkle_root,
+            time,
+            bits,
+            nonce,
+        }
+    }
+
+    /// The block hash in internal byte order.
+    pub fn block_hash(&self) -> [u8; 32] {
+        double_sha256(&self.to_bytes())
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut b = self.version.to_le_bytes().to_vec();
+        b.extend_from_slice(&self.prev_blockhash);
+        b.extend_from_slice(&self.merkle_root);
+        b.extend_from_slice(&self.time.to_le_bytes());
+        b.extend_from_slice(&self.bits.to_le_bytes());
+        b.extend_from_slice(&self.nonce.to_le_bytes());
+        b
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        if bytes.len() < Self::SIZE {
+            return Err(BitcoinError::InsufficientBytes);
+        }
+        let u32_at =
+            |i: usize| u32::from_le_bytes([bytes[i], bytes[i + 1], bytes[i + 2], bytes[i + 3]]);
+        let mut prev_blockhash = [0u8; 32];
+        prev_blockhash.copy_from_slice(&bytes[4..36]);
+        let mut merkle_root = [0u8; 32];
+        merkle_root.copy_from_slice(&bytes[36..68]);
+        Ok((
+            BlockHeader {
+                version: u32_at(0),
+                prev_blockhash,
+                merkle_root,
+                time: u32_at(68),
+                bits: u32_at(72),
+                nonce: u32_at(76),
+            },
+            Self::SIZE,
+        ))
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct Block {
+    pub header: BlockHeader,
+    pub transactions: Vec<BitcoinTransaction>,
+}
+
+impl Block {
+    pub fn new(header: BlockHeader, transactions: Vec<BitcoinTransaction>) -> Self {
+        Block {
+            header,
+            transactions,
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut b = self.header.to_bytes();
+        b.extend_from_slice(&serialize_block_body(&self.transactions));
+        b
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        let (header, mut offset) = BlockHeader::from_bytes(bytes)?;
+        let (count, count_len) = CompactSize::from_bytes(rest(bytes, offset)?)?;
+        offset = advance(offset, count_len)?;
+        let mut transactions = Vec::new();
+        for _ in 0..*count {
+            let (tx, tx_len) = BitcoinTransaction::from_bytes(rest(bytes, offset)?)?;
+            transactions.push(tx);
+            offset = advance(offset, tx_len)?;
+        }
+        Ok((
+            Block {
+                header,
+                transactions,
+            },
+            offset,
+        ))
+    }
+}
+
+/// Serializes the part of a block after its header: a CompactSize transaction
+/// count followed by each transaction.
+pub fn serialize_block_body(txs: &[BitcoinTransaction]) -> Vec<u8> {
+    let mut b = CompactSize::new(txs.len() as u64).to_bytes();
+    for tx in txs {
+        tx.encode_into(&mut b);
+    }
+    b
+}
+
+/// Splits a serialized block into its 80-byte header, the transaction count
+/// and the bytes after the count, without parsing any transaction. The
+/// transaction region runs to the end of `bytes`.
+pub fn split_block(bytes: &[u8]) -> Result<(&[u8], usize, &[u8]), BitcoinError> {
+    if bytes.len() < BlockHeader::SIZE {
+        return Err(BitcoinError::InsufficientBytes);
+    }
+    let (header, body) = bytes.split_at(BlockHeader::SIZE);
+    let (count, count_len) = CompactSize::from_bytes(body)?;
+    let count = usize::try_from(*count).map_err(|_| BitcoinError::InvalidFormat)?;
+    Ok((header, count, &body[count_len..]))
+}
+
+/// The merkle root over `txids` in block order, in internal byte order. An
+/// odd node at any level is paired with itself. An empty list gives all
+/// zeros.
+pub fn merkle_root(txids: &[Txid]) -> [u8; 32] {
+    let mut level: Vec<[u8; 32]> = txids.iter().map(Txid::to_raw_bytes).collect();
+    if level.is_empty() {
+        return [0; 32];
+    }
+    while level.len() > 1 {
+        level = level
+            .chunks(2)
+            .map(|pair| {
+                let right = pair.get(1).unwrap_or(&pair[0]);
+                double_sha256(&[pair[0], *right].concat())
+            })
+            .collect();
+    }
+    level[0]
+}
+
+/// Whether `txs`, in block order, hash to `expected_merkle_root`.
+pub fn verify_transactions(txs: &[BitcoinTransaction], expected_merkle_root: [u8; 32]) -> bool {
+    merkle_root(&compute_txids(txs)) == expected_merkle_root
+}
+
+/// Lazily parses the transactions of a block from the bytes following its
+/// header. Parsing stops at the first error, which is yielded once.
+#[derive(Debug, Clone)]
+pub struct TransactionIter<'a> {
+    bytes: &'a [u8],
+    remaining: u64,
+}
+
+impl<'a> TransactionIter<'a> {
+    /// Reads the transaction count at the start of `bytes`.
+    pub fn new(bytes: &'a [u8]) -> Result<Self, BitcoinError> {
+        let (count, count_len) = CompactSize::from_bytes(bytes)?;
+        Ok(TransactionIter {
+            bytes: &bytes[count_len..],
+            remaining: *count,
+        })
+    }
+
+    pub fn remaining(&self) -> u64 {
+        self.remaining
+    }
+}
+
+impl Iterator for TransactionIter<'_> {
+    type Item = Result<BitcoinTransaction, BitcoinError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        match BitcoinTransaction::from_bytes(self.bytes) {
+            Ok((tx, tx_len)) => {
+                self.bytes = &self.bytes[tx_len..];
+                self.remaining -= 1;
+                Some(Ok(tx))
+            }
+            Err(e) => {
+                self.remaining = 0;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// Walks the records of a Bitcoin Core `blk*.dat` file held in memory. Each
+/// record is a 4-byte network magic, a little-endian u32 size and that many
+/// bytes of block. Scanning stops quietly at zero padding or at a truncated or
+/// corrupt record. Returns the number of records passed to `f`.
+pub fn scan_block_records<F>(data: &[u8], mut f: F) -> usize
+where
+    F: FnMut(&BlockHeader, TransactionIter<'_>),
+{
+    let mut offset = 0;
+    let mut count = 0;
+    while data.len() - offset >= 8 {
+        if data[offset..offset + 4] == [0u8; 4] {
+            break;
+        }
+        let size = u32::from_le_bytes([
+            data[offset + 4],
+            data[offset + 5],
+            data[offset + 6],
+            data[offset + 7],
+        ]) as usize;
+        let start = offset + 8;
+        if data.len() - start < size {
+            break;
+        }
+        let record = &data[start..start + size];
+        let Ok((header, header_len)) = BlockHeader::from_bytes(record) else {
+            break;
+        };
+        let Ok(txs) = TransactionIter::new(&record[header_len..]) else {
+            break;
+        };
+        f(&header, txs);
+        count += 1;
+        offset = start + size;
+    }
+    count
+}
+
+/// Reads a block file into memory and scans it with `scan_block_records`.
+pub fn scan_block_file<F>(path: &Path, f: F) -> io::Result<usize>
+where
+    F: FnMut(&BlockHeader, TransactionIter<'_>),
+{
+    let data = std::fs::read(path)?;
+    Ok(scan_block_records(&data, f))
+}
+
+/// Like `scan_block_file`, but memory-maps the file instead of reading it, so
+/// only the pages being parsed need to be resident.
+///
+/// # Safety
+///
+/// The file must not be truncated or modified, by this or any other process,
+/// until the call returns. A change to a mapped file is undefined behaviour
+/// and can raise `SIGBUS`; this includes a running node appending to its
+/// current `blk*.dat` file. `scan_block_file` is the safe alternative.
+#[cfg(all(feature = "memmap", unix, target_pointer_width = "64"))]
+pub unsafe fn scan_block_file_mmap<F>(path: &Path, f: F) -> io::Result<usize>
+where
+    F: FnMut(&BlockHeader, TransactionIter<'_>),
+{
+    let file = std::fs::File::open(path)?;
+    // SAFETY: the caller guarantees the file stays unchanged until we return,
+    // and the mapping is dropped before then.
+    let map = unsafe { mmap::Mmap::map(&file)? };
+    Ok(scan_block_records(map.as_slice(), f))
+}
+
+#[cfg(all(feature = "memmap", unix, target_pointer_width = "64"))]
+mod mmap {
+    use std::fs::File;
+    use std::io;
+    use std::os::raw::{c_int, c_void};
+    use std::os::unix::io::AsRawFd;
+
+    const PROT_READ: c_int = 1;
+    const MAP_PRIVATE: c_int = 2;
+
+    unsafe extern "C" {
+        fn mmap(
+            addr: *mut c_void,
+            len: usize,
+            prot: c_int,
+            flags: c_int,
+            fd: c_int,
+            offset: i64,
+        ) -> *mut c_void;
+        fn munmap(addr: *mut c_void, len: usize) -> c_int;
+    }
+
+    /// A read-only private mapping of a whole file.
+    pub struct Mmap {
+        ptr: *mut c_void,
+        len: usize,
+    }
+
+    impl Mmap {
+        /// Maps the whole of `file` read-only.
+        ///
+        /// # Safety
+        ///
+        /// The file must not be truncated or modified while the returned
+        /// mapping is alive, since `as_slice` hands its contents out as an
+        /// immutable `&[u8]`.
+        pub unsafe fn map(file: &File) -> io::Result<Self> {
+            let len = usize::try_from(file.metadata()?.len())
+                .map_err(|_| io::Error::from(io::ErrorKind::InvalidInput))?;
+            if len == 0 {
+                return Ok(Mmap {
+                    ptr: std::ptr::null_mut(),
+                    len: 0,
+                });
+            }
+            // SAFETY: a fresh read-only mapping of an open file descriptor that
+            // overlaps no existing memory; the result is checked against
+            // MAP_FAILED before use.
+            let ptr = unsafe {
+                mmap(
+                    std::ptr::null_mut(),
+                    len,
+                    PROT_READ,
+                    MAP_PRIVATE,
+                    file.as_raw_fd(),
+                    0,
+                )
+            };
+            if ptr as isize == -1 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(Mmap { ptr, len })
+        }
+
+        pub fn as_slice(&self) -> &[u8] {
+            if self.len == 0 {
+                return &[];
+            }
+            // SAFETY: `ptr` maps `len` readable bytes until `self` is dropped,
+            // and the caller of `map` guaranteed the file does not change in
+            // that time.
+            unsafe { std::slice::from_raw_parts(self.ptr as *const u8, self.len) }
+        }
+    }
+
+    impl Drop for Mmap {
+        fn drop(&mut self) {
+            if self.len > 0 {
+                // SAFETY: unmapping exactly the region returned by `mmap`.
+                unsafe {
+                    munmap(self.ptr, self.len);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,166 @@
+use crate::amount::{Amount, FeeRate};
+use crate::policy::{self, StandardnessViolation};
+use crate::script::MAX_SCRIPT_SIZE;
+use crate::{
+    BitcoinError, BitcoinTransaction, OutPoint, SEQUENCE_ENABLE_RBF, SEQUENCE_FINAL, Script,
+    TransactionInput, TransactionOutput, Witness,
+};
+
+/// Assembles an unsigned transaction. Inputs are added with empty scriptSigs
+/// and witnesses, ready for a signer to fill in.
+#[derive(Debug, Clone)]
+pub struct TransactionBuilder {
+    version: u32,
+    lock_time: u32,
+    inputs: Vec<TransactionInput>,
+    outputs: Vec<TransactionOutput>,
+    rbf: bool,
+}
+
+impl Default for TransactionBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TransactionBuilder {
+    pub fn new() -> Self {
+        TransactionBuilder {
+            version: 2,
+            lock_time: 0,
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+            rbf: false,
+        }
+    }
+
+    pub fn version(mut self, version: u32) -> Self {
+        self.version = version;
+        self
+    }
+
+    pub fn lock_time(mut self, lock_time: u32) -> Self {
+        self.lock_time = lock_time;
+        self
+    }
+
+    /// When enabled, inputs added afterwards with `add_input` get
+    /// `SEQUENCE_ENABLE_RBF` instead of `SEQUENCE_FINAL`. Sequences passed to
+    /// `add_input_with_sequence`, such as relative lock times, are kept.
+    pub fn enable_rbf(mut self, rbf: bool) -> Self {
+        self.rbf = rbf;
+        self
+    }
+
+    pub fn add_input(self, previous_output: OutPoint) -> Self {
+        let sequence = if self.rbf {
+            SEQUENCE_ENABLE_RBF
+        } else {
+            SEQUENCE_FINAL
+        };
+        self.add_input_with_sequence(previous_output, sequence)
+    }
+
+    pub fn add_input_with_sequence(mut self, previous_output: OutPoint, sequence: u32) -> Self {
+        self.inputs.push(TransactionInput::new(
+            previous_output,
+            Script::new(vec![]),
+            sequence,
+        ));
+        self
+    }
+
+    pub fn add_output(mut self, value: Amount, script_pubkey: Script) -> Self {
+        self.outputs
+            .push(TransactionOutput::new(value.to_sat(), script_pubkey));
+        self
+    }
+
+    /// Returns the transaction as configured, without any policy checks.
+    pub fn build(&self) -> BitcoinTransaction {
+        let mut tx = BitcoinTransaction::new(self.version, self.inputs.clone(), self.lock_time);
+        tx.outputs = self.outputs.clone();
+        tx
+    }
+
+    /// Like `build`, but runs the structural and dust checks from
+    /// `policy::check_standardness` first and returns every violation found.
+    pub fn build_checked(
+        &self,
+        dust_relay_fee: FeeRate,
+    ) -> Result<BitcoinTransaction, Vec<StandardnessViolation>> {
+        let tx = self.build();
+        policy::check_standardness(&tx, dust_relay_fee)?;
+        Ok(tx)
+    }
+}
+
+impl BitcoinTransaction {
+    /// A version 2 transaction spending `prevout`, worth `input_value`, to a
+    /// single output paying `amount` to `recipient`. There is no change
+    /// output, so any surplus of `input_value` over `amount` plus `fee` goes
+    /// to the miner as extra fee. Fails with `InvalidFormat` when
+    /// `input_value` does not cover `amount` plus `fee`.
+    pub fn simple_transfer(
+        prevout: OutPoint,
+        script_sig: Script,
+        recipient: Script,
+        amount: Amount,
+        fee: Amount,
+        input_value: Amount,
+    ) -> Result<Self, BitcoinError> {
+        let needed = amount.checked_add(fee).ok_or(BitcoinError::InvalidFormat)?;
+        if input_value < needed {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        let mut tx = TransactionBuilder::new()
+            .add_input(prevout)
+            .add_output(amount, recipient)
+            .build();
+        tx.inputs[0].script_sig = script_sig;
+        Ok(tx)
+    }
+}
+
+/// Assembles a witness stack item by item, checking each item's size.
+#[derive(Debug, Clone, Default)]
+pub struct WitnessBuilder {
+    items: Vec<Vec<u8>>,
+}
+
+impl WitnessBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Pushes a DER signature with its trailing sighash byte, 9 to 73 bytes.
+    pub fn push_signature(mut self, der_with_type: &[u8]) -> Result<Self, BitcoinError> {
+        if !(9..=73).contains(&der_with_type.len()) {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        self.items.push(der_with_type.to_vec());
+        Ok(self)
+    }
+
+    /// Pushes a 33-byte compressed or 65-byte uncompressed public key.
+    pub fn push_key(mut self, pubkey: &[u8]) -> Result<Self, BitcoinError> {
+        if !matches!(pubkey.len(), 33 | 65) {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        self.items.push(pubkey.to_vec());
+        Ok(self)
+    }
+
+    /// Pushes a witness script, which may be at most `MAX_SCRIPT_SIZE` bytes.
+    pub fn push_script(mut self, script: &Script) -> Result<Self, BitcoinError> {
+        if script.len() > MAX_SCRIPT_SIZE {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        self.items.push(script.bytes.clone());
+        Ok(self)
+    }
+
+    pub fn build(self) -> Witness {
+        Witness::new(self.items)
+    }
+}
@@ -0,0 +1,143 @@
+use crate::address::Network;
+use crate::{Amount, BitcoinTransaction, CompactSize};
+use std::ops::Range;
+
+impl BitcoinTransaction {
+    /// Splits the `to_bytes` serialization into labeled fields, in order.
+    /// Zero-length fields, such as an empty scriptSig, are left out.
+    fn labeled_fields(&self) -> Vec<(String, Vec<u8>)> {
+        let mut fields = Vec::new();
+        let mut push = |label: String, bytes: Vec<u8>| {
+            if !bytes.is_empty() {
+                fields.push((label, bytes));
+            }
+        };
+        let count = |n: usize| CompactSize::new(n as u64).to_bytes();
+        let segwit = self.has_witness();
+
+        push("version".to_string(), self.version.to_le_bytes().to_vec());
+        if segwit {
+            push("marker".to_string(), vec![0x00]);
+            push("flag".to_string(), vec![0x01]);
+        }
+        push("input count".to_string(), count(self.inputs.len()));
+        for (i, input) in self.inputs.iter().enumerate() {
+            let script_sig = &input.script_sig.bytes;
+            push(
+                format!("input[{}] txid", i),
+                input.previous_output.txid.0.to_vec(),
+            );
+            push(
+                format!("input[{}] vout", i),
+                input.previous_output.vout.to_le_bytes().to_vec(),
+            );
+            push(
+                format!("input[{}] scriptSig length", i),
+                count(script_sig.len()),
+            );
+            push(format!("input[{}] scriptSig", i), script_sig.clone());
+            push(
+                format!("input[{}] sequence", i),
+                input.sequence.to_le_bytes().to_vec(),
+            );
+        }
+        push("output count".to_string(), count(self.outputs.len()));
+        for (i, output) in self.outputs.iter().enumerate() {
+            let script_pubkey = &output.script_pubkey.bytes;
+            push(
+                format!("output[{}] value", i),
+                output.value.to_le_bytes().to_vec(),
+            );
+            push(
+                format!("output[{}] scriptPubKey length", i),
+                count(script_pubkey.len()),
+            );
+            push(format!("output[{}] scriptPubKey", i), script_pubkey.clone());
+        }
+        if segwit {
+            for (i, input) in self.inputs.iter().enumerate() {
+                push(
+                    format!("witness[{}] item count", i),
+                    count(input.witness.len()),
+                );
+                for (j, item) in input.witness.items.iter().enumerate() {
+                    push(format!("witness[{}][{}] length", i, j), count(item.len()));
+                    push(format!("witness[{}][{}]", i, j), item.clone());
+                }
+            }
+        }
+        push(
+            "lock_time".to_string(),
+            self.lock_time.to_le_bytes().to_vec(),
+        );
+        fields
+    }
+
+    /// Renders `to_bytes` as a hex dump with one logical field per line: the
+    /// byte offset, the field name and its bytes.
+    pub fn annotated_hex(&self) -> String {
+        let mut out = String::new();
+        let mut offset = 0;
+        for (label, bytes) in self.labeled_fields() {
+            out.push_str(&format!(
+                "{:04x}  {:<30} {}\n",
+                offset,
+                label,
+                hex::encode(&bytes)
+            ));
+            offset += bytes.len();
+        }
+        out
+    }
+
+    /// The byte range each logical field occupies in `to_bytes`, labeled as in
+    /// `annotated_hex` and including witness fields when present. The ranges
+    /// are in order and cover the serialization exactly; zero-length fields
+    /// are left out.
+    pub fn field_ranges(&self) -> Vec<(String, Range<usize>)> {
+        let mut offset = 0;
+        self.labeled_fields()
+            .into_iter()
+            .map(|(label, bytes)| {
+                let start = offset;
+                offset += bytes.len();
+                (label, start..offset)
+            })
+            .collect()
+    }
+
+    /// Renders the value flow as a Graphviz DOT graph: one node per spent
+    /// outpoint, a node for the transaction and one node per output, labeled
+    /// with its value and mainnet address (or script type when it has none).
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("digraph transaction {\n    rankdir=LR;\n");
+        out.push_str(&format!(
+            "    tx [shape=box, label=\"{}\"];\n",
+            self.txid().to_display_string()
+        ));
+        for (i, input) in self.inputs.iter().enumerate() {
+            let outpoint = &input.previous_output;
+            out.push_str(&format!(
+                "    in{} [label=\"{}:{}\"];\n    in{} -> tx;\n",
+                i,
+                outpoint.txid.to_display_string(),
+                outpoint.vout,
+                i
+            ));
+        }
+        for (i, output) in self.outputs.iter().enumerate() {
+            let destination = output
+                .address(Network::Bitcoin)
+                .unwrap_or_else(|| format!("{:?}", output.script_pubkey.script_type()));
+            out.push_str(&format!(
+                "    out{} [label=\"{}\\n{}\"];\n    tx -> out{};\n",
+                i,
+                Amount(output.value),
+                destination,
+                i
+            ));
+        }
+        out.push_str("}\n");
+        out
+    }
+}
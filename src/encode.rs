@@ -0,0 +1,48 @@
+use crate::block::{Block, BlockHeader};
+use crate::{
+    BitcoinError, BitcoinTransaction, CompactSize, OutPoint, Script, TransactionInput,
+    TransactionOutput, Witness,
+};
+
+/// A type with a Bitcoin wire encoding.
+pub trait Encodable {
+    fn consensus_encode(&self) -> Vec<u8>;
+}
+
+/// A type that can be read back from its Bitcoin wire encoding. Returns the
+/// value and the number of bytes consumed; trailing bytes are left alone.
+pub trait Decodable: Sized {
+    fn consensus_decode(bytes: &[u8]) -> Result<(Self, usize), BitcoinError>;
+}
+
+// Every wire type already has inherent `to_bytes`/`from_bytes`; the traits
+// just make them usable from generic code.
+macro_rules! impl_consensus_codec {
+    ($($ty:ty),* $(,)?) => {
+        $(
+            impl Encodable for $ty {
+                fn consensus_encode(&self) -> Vec<u8> {
+                    self.to_bytes()
+                }
+            }
+
+            impl Decodable for $ty {
+                fn consensus_decode(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+                    <$ty>::from_bytes(bytes)
+                }
+            }
+        )*
+    };
+}
+
+impl_consensus_codec!(
+    CompactSize,
+    OutPoint,
+    Script,
+    Witness,
+    TransactionInput,
+    TransactionOutput,
+    BitcoinTransaction,
+    BlockHeader,
+    Block,
+);
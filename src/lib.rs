@@ -1,8 +1,31 @@
+use hashes::double_sha256;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
 use std::fmt;
+use std::io;
 use std::ops::Deref;
 
-#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub mod address;
+pub mod amount;
+pub mod block;
+pub mod builder;
+mod dump;
+pub mod encode;
+pub mod hashes;
+pub mod locktime;
+pub mod p2p;
+pub mod policy;
+pub mod prelude;
+pub mod psbt;
+pub mod script;
+pub mod sighash;
+
+pub use address::Network;
+pub use amount::{Amount, FeeRate};
+pub use builder::{TransactionBuilder, WitnessBuilder};
+pub use script::ScriptType;
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Serialize, Deserialize)]
 pub struct CompactSize {
     pub value: u64,
 }
@@ -11,6 +34,13 @@ pub struct CompactSize {
 pub enum BitcoinError {
     InsufficientBytes,
     InvalidFormat,
+    OversizedTransaction,
+}
+
+impl From<hex::FromHexError> for BitcoinError {
+    fn from(_: hex::FromHexError) -> Self {
+        BitcoinError::InvalidFormat
+    }
 }
 
 impl CompactSize {
@@ -37,6 +67,16 @@ impl CompactSize {
         }
     }
 
+    /// The length of `to_bytes` without building it.
+    pub fn serialized_len(&self) -> usize {
+        match self.value {
+            0..=0xFC => 1,
+            0xFD..=0xFFFF => 3,
+            0x1_0000..=0xFFFF_FFFF => 5,
+            _ => 9,
+        }
+    }
+
     pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
         if let Some((&prefix, rest)) = bytes.split_first() {
             match prefix {
@@ -74,17 +114,193 @@ impl CompactSize {
             Err(BitcoinError::InsufficientBytes)
         }
     }
+
+    /// `from_bytes`, failing with `InvalidFormat` when the value exceeds
+    /// `max`. Parsers use this to reject absurd element counts up front.
+    pub fn from_bytes_bounded(bytes: &[u8], max: u64) -> Result<(Self, usize), BitcoinError> {
+        let (size, len) = Self::from_bytes(bytes)?;
+        if size.value > max {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        Ok((size, len))
+    }
 }
 
-#[derive(Debug, PartialEq, Eq, Clone)]
+impl fmt::Display for CompactSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.value)
+    }
+}
+
+impl Deref for CompactSize {
+    type Target = u64;
+    fn deref(&self) -> &Self::Target {
+        &self.value
+    }
+}
+
+/// Computes the txid of a hex-encoded raw transaction. The transaction is
+/// parsed to validate it, but a legacy serialization is hashed as given rather
+/// than re-encoded. Trailing bytes are rejected with `InvalidFormat`.
+pub fn txid_from_raw_hex(hex: &str) -> Result<Txid, BitcoinError> {
+    let bytes = hex::decode(hex)?;
+    let (tx, consumed) = BitcoinTransaction::from_bytes(&bytes)?;
+    if consumed != bytes.len() {
+        return Err(BitcoinError::InvalidFormat);
+    }
+    if tx.has_witness() {
+        Ok(tx.txid())
+    } else {
+        Ok(Txid(double_sha256(&bytes)))
+    }
+}
+
+/// The txids of `txs`, in order. With the `parallel` feature the hashing is
+/// spread over `std::thread::scope` workers; otherwise it is a plain
+/// sequential map.
+///
+/// The feature is called `parallel` rather than `rayon` because it uses only
+/// the standard library, so it adds no dependency. The thread count is
+/// `available_parallelism()` (one if that is unknown), and `txs` is cut into at
+/// most that many contiguous chunks of `len / threads` rounded up, so no chunk
+/// is empty and the results are joined back in input order.
+pub fn compute_txids(txs: &[BitcoinTransaction]) -> Vec<Txid> {
+    #[cfg(feature = "parallel")]
+    {
+        let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+        let chunk_size = txs.len().div_ceil(threads).max(1);
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = txs
+                .chunks(chunk_size)
+                .map(|chunk| {
+                    scope.spawn(move || {
+                        chunk
+                            .iter()
+                            .map(BitcoinTransaction::txid)
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().expect("txid worker panicked"))
+                .collect()
+        })
+    }
+    #[cfg(not(feature = "parallel"))]
+    {
+        txs.iter().map(BitcoinTransaction::txid).collect()
+    }
+}
+
+/// Whether `a` and `b` are the same transaction with different witnesses: the
+/// txids match but the wtxids do not, as when a witness is replaced in the
+/// mempool.
+pub fn is_witness_malleation(a: &BitcoinTransaction, b: &BitcoinTransaction) -> bool {
+    a.txid() == b.txid() && a.wtxid() != b.wtxid()
+}
+
+/// Returns the bytes from `offset` onwards, or `InsufficientBytes` if `offset`
+/// is past the end. The parsers use this instead of slicing directly so that no
+/// truncated input can make them panic.
+pub(crate) fn rest(bytes: &[u8], offset: usize) -> Result<&[u8], BitcoinError> {
+    bytes.get(offset..).ok_or(BitcoinError::InsufficientBytes)
+}
+
+/// `offset + len` for the parsers' position bookkeeping. A sum that would
+/// overflow `usize`, only reachable with crafted lengths on narrow targets,
+/// fails with `InvalidFormat` instead of wrapping.
+pub(crate) fn advance(offset: usize, len: usize) -> Result<usize, BitcoinError> {
+    offset.checked_add(len).ok_or(BitcoinError::InvalidFormat)
+}
+
+/// Encodes `data` as a CompactSize length followed by the bytes themselves.
+pub fn write_var_bytes(data: &[u8]) -> Vec<u8> {
+    let mut b = CompactSize::new(data.len() as u64).to_bytes();
+    b.extend_from_slice(data);
+    b
+}
+
+/// Decodes a CompactSize length followed by that many bytes, returning the
+/// bytes and the total number consumed. The declared length is checked against
+/// the remaining input before anything is allocated, so a bogus length cannot
+/// trigger a huge allocation.
+pub fn read_var_bytes(bytes: &[u8]) -> Result<(Vec<u8>, usize), BitcoinError> {
+    let (len, prefix_len) = CompactSize::from_bytes(bytes)?;
+    let remaining = bytes.len() - prefix_len;
+    if *len > remaining as u64 {
+        return Err(BitcoinError::InsufficientBytes);
+    }
+    let total_len = advance(prefix_len, *len as usize)?;
+    Ok((bytes[prefix_len..total_len].to_vec(), total_len))
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
 pub struct Txid(pub [u8; 32]);
 
+// A txid is stored in internal byte order: the order the double-SHA256 digest
+// comes out in and the order used inside serialized transactions. Block
+// explorers and RPC display the same 32 bytes reversed.
+impl Txid {
+    /// The all-zero txid referenced by a coinbase input's null outpoint.
+    pub fn all_zeros() -> Self {
+        Txid([0; 32])
+    }
+
+    /// Builds a txid from bytes in internal order.
+    pub fn from_raw_bytes(bytes: [u8; 32]) -> Self {
+        Txid(bytes)
+    }
+
+    /// Parses the reversed hex form shown by explorers and RPC.
+    pub fn from_display_hex(s: &str) -> Result<Self, BitcoinError> {
+        let bytes = hex::decode(s)?;
+        let mut arr: [u8; 32] = bytes.try_into().map_err(|_| BitcoinError::InvalidFormat)?;
+        arr.reverse();
+        Ok(Txid(arr))
+    }
+
+    /// Returns the bytes in internal order.
+    pub fn to_raw_bytes(&self) -> [u8; 32] {
+        self.0
+    }
+
+    /// Returns the reversed hex form shown by explorers and RPC.
+    pub fn to_display_string(&self) -> String {
+        let mut bytes = self.0;
+        bytes.reverse();
+        hex::encode(bytes)
+    }
+}
+
+// Txids order like their displayed hex, which is the order BIP69 sorts
+// inputs by.
+impl Ord for Txid {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.0.iter().rev().cmp(other.0.iter().rev())
+    }
+}
+
+impl PartialOrd for Txid {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// Txids serialize as the reversed hex shown by explorers and RPC. Use
+// `#[serde(with = "txid_internal_hex")]` on a field to get internal order.
+// Binary formats such as bincode get the 32 raw bytes in internal order
+// instead of a 64-character string.
 impl Serialize for Txid {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: serde::Serializer,
     {
-        serializer.serialize_str(&hex::encode(self.0))
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&self.to_display_string())
+        } else {
+            self.0.serialize(serializer)
+        }
     }
 }
 
@@ -93,6 +309,39 @@ impl<'de> Deserialize<'de> for Txid {
     where
         D: serde::Deserializer<'de>,
     {
+        let human_readable = deserializer.is_human_readable();
+        let mut txid = txid_internal_hex::deserialize(deserializer)?;
+        if human_readable {
+            txid.0.reverse();
+        }
+        Ok(txid)
+    }
+}
+
+/// Serde adapter encoding a `Txid` as hex in internal byte order. Binary
+/// formats get the raw bytes, exactly as with the default encoding.
+pub mod txid_internal_hex {
+    use super::Txid;
+    use serde::{Deserialize, Serialize};
+
+    pub fn serialize<S>(txid: &Txid, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&hex::encode(txid.0))
+        } else {
+            txid.0.serialize(serializer)
+        }
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Txid, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        if !deserializer.is_human_readable() {
+            return <[u8; 32]>::deserialize(deserializer).map(Txid);
+        }
         let s = String::deserialize(deserializer)?;
         let bytes = hex::decode(&s).map_err(serde::de::Error::custom)?;
         if bytes.len() != 32 {
@@ -104,7 +353,7 @@ impl<'de> Deserialize<'de> for Txid {
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Hash, Serialize, Deserialize)]
 pub struct OutPoint {
     pub txid: Txid,
     pub vout: u32,
@@ -118,6 +367,35 @@ impl OutPoint {
         }
     }
 
+    /// The outpoint a coinbase input spends: the all-zero txid and vout
+    /// `0xFFFFFFFF`.
+    pub fn null() -> Self {
+        OutPoint {
+            txid: Txid::all_zeros(),
+            vout: u32::MAX,
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        *self == Self::null()
+    }
+
+    /// The `txid:vout` form used by RPC and CLI tools, with the txid in
+    /// display order.
+    pub fn to_rpc_string(&self) -> String {
+        format!("{}:{}", self.txid.to_display_string(), self.vout)
+    }
+
+    /// Parses `to_rpc_string` output. A missing colon, bad txid hex or a vout
+    /// that is not a `u32` is `InvalidFormat`.
+    pub fn from_rpc_string(s: &str) -> Result<OutPoint, BitcoinError> {
+        let (txid, vout) = s.split_once(':').ok_or(BitcoinError::InvalidFormat)?;
+        Ok(OutPoint {
+            txid: Txid::from_display_hex(txid)?,
+            vout: vout.parse().map_err(|_| BitcoinError::InvalidFormat)?,
+        })
+    }
+
     pub fn to_bytes(&self) -> Vec<u8> {
         let mut b = self.txid.0.to_vec();
         b.extend_from_slice(&self.vout.to_le_bytes());
@@ -141,7 +419,7 @@ impl OutPoint {
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Serialize, Deserialize)]
 pub struct Script {
     pub bytes: Vec<u8>,
 }
@@ -152,18 +430,27 @@ impl Script {
     }
 
     pub fn to_bytes(&self) -> Vec<u8> {
-        let mut size = CompactSize::new(self.bytes.len() as u64).to_bytes();
-        size.extend_from_slice(&self.bytes);
-        size
+        write_var_bytes(&self.bytes)
+    }
+
+    pub fn serialized_len(&self) -> usize {
+        CompactSize::new(self.bytes.len() as u64).serialized_len() + self.bytes.len()
+    }
+
+    /// Parses the raw script hex that RPC shows for a scriptPubKey. Unlike
+    /// `from_bytes`, there is no CompactSize length prefix: every byte is
+    /// part of the script.
+    pub fn from_hex(s: &str) -> Result<Script, BitcoinError> {
+        Ok(Script::new(hex::decode(s)?))
+    }
+
+    /// The raw script as hex, without the length prefix `to_bytes` adds.
+    pub fn to_hex(&self) -> String {
+        hex::encode(&self.bytes)
     }
 
     pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
-        let (len_prefix, prefix_len) = CompactSize::from_bytes(bytes)?;
-        let total_len = prefix_len + (len_prefix.value as usize);
-        if bytes.len() < total_len {
-            return Err(BitcoinError::InsufficientBytes);
-        }
-        let script = bytes[prefix_len..total_len].to_vec();
+        let (script, total_len) = read_var_bytes(bytes)?;
         Ok((Script { bytes: script }, total_len))
     }
 }
@@ -175,11 +462,171 @@ impl Deref for Script {
     }
 }
 
+pub const SEQUENCE_FINAL: u32 = 0xFFFF_FFFF;
+
+/// The sequence wallets use to opt in to BIP125 replacement while keeping
+/// relative lock times disabled and the lock time enforced.
+pub const SEQUENCE_ENABLE_RBF: u32 = 0xFFFF_FFFD;
+
+/// BIP68: when set in a sequence, the input has no relative lock time.
+pub const SEQUENCE_LOCKTIME_DISABLE_FLAG: u32 = 1 << 31;
+
+/// Most stack items a witness may carry when parsed in strict mode.
+pub const MAX_WITNESS_STACK_ITEMS: u64 = 100;
+
+/// Consensus limit on the serialized size of a transaction.
+pub const MAX_TX_SIZE: usize = 1_000_000;
+
+/// Options controlling how permissive the parsers are. The default accepts
+/// anything that is structurally well formed; `strict` additionally enforces
+/// the limits a validator applies to untrusted data.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ParseConfig {
+    pub strict: bool,
+    /// Transactions longer than this many bytes are rejected with
+    /// `OversizedTransaction`, without reading past the limit.
+    pub max_tx_size: usize,
+    /// When false the BIP144 marker and flag are never looked for and every
+    /// transaction is read in the legacy layout.
+    pub allow_witness: bool,
+}
+
+impl Default for ParseConfig {
+    fn default() -> Self {
+        ParseConfig {
+            strict: false,
+            max_tx_size: MAX_TX_SIZE,
+            allow_witness: true,
+        }
+    }
+}
+
+impl ParseConfig {
+    pub fn strict() -> Self {
+        ParseConfig {
+            strict: true,
+            ..ParseConfig::default()
+        }
+    }
+}
+
+/// First byte of a BIP341 taproot annex.
+pub const TAPROOT_ANNEX_PREFIX: u8 = 0x50;
+
+/// How a taproot output is being spent.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum TaprootSpendKind {
+    KeyPath,
+    ScriptPath,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Default, Serialize, Deserialize)]
+pub struct Witness {
+    pub items: Vec<Vec<u8>>,
+}
+
+impl Witness {
+    pub fn new(items: Vec<Vec<u8>>) -> Self {
+        Witness { items }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn push(&mut self, item: Vec<u8>) {
+        self.items.push(item);
+    }
+
+    pub fn get(&self, i: usize) -> Option<&[u8]> {
+        self.items.get(i).map(Vec::as_slice)
+    }
+
+    /// The BIP341 annex: the last item, when there are at least two and it
+    /// starts with `0x50`.
+    pub fn taproot_annex(&self) -> Option<&[u8]> {
+        match self.items.as_slice() {
+            [_, .., last] if last.first() == Some(&TAPROOT_ANNEX_PREFIX) => Some(last),
+            _ => None,
+        }
+    }
+
+    /// Reads this witness as a taproot spend. After dropping any annex, a
+    /// single item is a key-path signature; two or more items ending in a
+    /// control block (33 bytes plus up to 128 32-byte hashes) are a script
+    /// path. Anything else gives `None`.
+    pub fn taproot_spend_kind(&self) -> Option<TaprootSpendKind> {
+        let items = match self.taproot_annex() {
+            Some(_) => &self.items[..self.items.len() - 1],
+            None => &self.items[..],
+        };
+        match items {
+            [_] => Some(TaprootSpendKind::KeyPath),
+            [_, .., control]
+                if control.len() >= 33
+                    && (control.len() - 33) % 32 == 0
+                    && (control.len() - 33) / 32 <= 128 =>
+            {
+                Some(TaprootSpendKind::ScriptPath)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut b = CompactSize::new(self.items.len() as u64).to_bytes();
+        for item in &self.items {
+            b.extend_from_slice(&write_var_bytes(item));
+        }
+        b
+    }
+
+    pub fn serialized_len(&self) -> usize {
+        let items: usize = self
+            .items
+            .iter()
+            .map(|item| CompactSize::new(item.len() as u64).serialized_len() + item.len())
+            .sum();
+        CompactSize::new(self.items.len() as u64).serialized_len() + items
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        Self::from_bytes_with_config(bytes, &ParseConfig::default())
+    }
+
+    /// Parses a witness stack. In strict mode more than
+    /// `MAX_WITNESS_STACK_ITEMS` items is rejected with `InvalidFormat`.
+    pub fn from_bytes_with_config(
+        bytes: &[u8],
+        config: &ParseConfig,
+    ) -> Result<(Self, usize), BitcoinError> {
+        let max_items = if config.strict {
+            MAX_WITNESS_STACK_ITEMS
+        } else {
+            u64::MAX
+        };
+        let (count, mut offset) = CompactSize::from_bytes_bounded(bytes, max_items)?;
+        let mut items = Vec::new();
+        for _ in 0..*count {
+            let (item, item_len) = read_var_bytes(rest(bytes, offset)?)?;
+            items.push(item);
+            offset = advance(offset, item_len)?;
+        }
+        Ok((Witness { items }, offset))
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
 pub struct TransactionInput {
     pub previous_output: OutPoint,
     pub script_sig: Script,
     pub sequence: u32,
+    #[serde(default)]
+    pub witness: Witness,
 }
 
 impl TransactionInput {
@@ -188,9 +635,56 @@ impl TransactionInput {
             previous_output,
             script_sig,
             sequence,
+            witness: Witness::default(),
         }
     }
 
+    pub fn outpoint(&self) -> &OutPoint {
+        &self.previous_output
+    }
+
+    pub fn with_script_sig(mut self, script_sig: Script) -> Self {
+        self.script_sig = script_sig;
+        self
+    }
+
+    pub fn with_sequence(mut self, sequence: u32) -> Self {
+        self.sequence = sequence;
+        self
+    }
+
+    pub fn is_final(&self) -> bool {
+        self.sequence == SEQUENCE_FINAL
+    }
+
+    /// BIP125 opt-in replace-by-fee: any sequence below `0xFFFFFFFE`.
+    pub fn signals_rbf(&self) -> bool {
+        self.sequence < SEQUENCE_FINAL - 1
+    }
+
+    /// Whether the sequence encodes a BIP68 relative lock time, i.e. the
+    /// disable flag (bit 31) is clear. BIP68 is only enforced for
+    /// transactions of version 2 or higher.
+    pub fn has_relative_lock_time(&self) -> bool {
+        self.sequence & SEQUENCE_LOCKTIME_DISABLE_FLAG == 0
+    }
+
+    /// The weight this input adds to its transaction: its `to_bytes` length
+    /// times four plus its serialized witness, if it has one. The empty
+    /// witness an input without one gets in a segwit transaction is left to
+    /// the shared overhead, along with the version, counts, outputs, lock
+    /// time and marker.
+    pub fn input_weight(&self) -> usize {
+        let witness_len = if self.witness.is_empty() {
+            0
+        } else {
+            self.witness.serialized_len()
+        };
+        self.serialized_len() * 4 + witness_len
+    }
+
+    /// Serializes the input as it appears in the input list. The witness is
+    /// not part of this encoding; it is written separately by the transaction.
     pub fn to_bytes(&self) -> Vec<u8> {
         let mut b = self.previous_output.to_bytes();
         b.extend_from_slice(&self.script_sig.to_bytes());
@@ -198,25 +692,80 @@ impl TransactionInput {
         b
     }
 
+    /// The length of `to_bytes`, which excludes the witness.
+    pub fn serialized_len(&self) -> usize {
+        36 + self.script_sig.serialized_len() + 4
+    }
+
     pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
         let (outpoint, offset1) = OutPoint::from_bytes(bytes)?;
-        let (script, offset2) = Script::from_bytes(&bytes[offset1..])?;
-        if bytes.len() < offset1 + offset2 + 4 {
+        let (script, offset2) = Script::from_bytes(rest(bytes, offset1)?)?;
+        let sequence_start = advance(offset1, offset2)?;
+        let end = advance(sequence_start, 4)?;
+        if bytes.len() < end {
             return Err(BitcoinError::InsufficientBytes);
         }
         let seq = u32::from_le_bytes([
-            bytes[offset1 + offset2],
-            bytes[offset1 + offset2 + 1],
-            bytes[offset1 + offset2 + 2],
-            bytes[offset1 + offset2 + 3],
+            bytes[sequence_start],
+            bytes[sequence_start + 1],
+            bytes[sequence_start + 2],
+            bytes[sequence_start + 3],
         ]);
         Ok((
             TransactionInput {
                 previous_output: outpoint,
                 script_sig: script,
                 sequence: seq,
+                witness: Witness::default(),
             },
-            offset1 + offset2 + 4,
+            end,
+        ))
+    }
+}
+
+impl AsRef<OutPoint> for TransactionInput {
+    fn as_ref(&self) -> &OutPoint {
+        &self.previous_output
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Serialize, Deserialize)]
+pub struct TransactionOutput {
+    pub value: u64,
+    pub script_pubkey: Script,
+}
+
+impl TransactionOutput {
+    pub fn new(value: u64, script_pubkey: Script) -> Self {
+        TransactionOutput {
+            value,
+            script_pubkey,
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut b = self.value.to_le_bytes().to_vec();
+        b.extend_from_slice(&self.script_pubkey.to_bytes());
+        b
+    }
+
+    pub fn serialized_len(&self) -> usize {
+        8 + self.script_pubkey.serialized_len()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        if bytes.len() < 8 {
+            return Err(BitcoinError::InsufficientBytes);
+        }
+        let mut value = [0u8; 8];
+        value.copy_from_slice(&bytes[0..8]);
+        let (script, script_len) = Script::from_bytes(rest(bytes, 8)?)?;
+        Ok((
+            TransactionOutput {
+                value: u64::from_le_bytes(value),
+                script_pubkey: script,
+            },
+            advance(8, script_len)?,
         ))
     }
 }
@@ -225,6 +774,8 @@ impl TransactionInput {
 pub struct BitcoinTransaction {
     pub version: u32,
     pub inputs: Vec<TransactionInput>,
+    #[serde(default)]
+    pub outputs: Vec<TransactionOutput>,
     pub lock_time: u32,
 }
 
@@ -233,49 +784,578 @@ impl BitcoinTransaction {
         BitcoinTransaction {
             version,
             inputs,
+            outputs: Vec::new(),
             lock_time,
         }
     }
 
+    /// A copy of the transaction with `version` in place of the current one.
+    pub fn with_version(&self, version: u32) -> BitcoinTransaction {
+        BitcoinTransaction {
+            version,
+            ..self.clone()
+        }
+    }
+
+    /// Whether BIP68 relative lock times apply, which needs version 2 or
+    /// later.
+    pub fn enforces_bip68(&self) -> bool {
+        self.version >= 2
+    }
+
+    pub fn is_final(&self) -> bool {
+        self.inputs.iter().all(TransactionInput::is_final)
+    }
+
+    pub fn has_witness(&self) -> bool {
+        self.inputs.iter().any(|input| !input.witness.is_empty())
+    }
+
+    /// The outpoints spent by the inputs, in input order.
+    pub fn spent_outpoints(&self) -> impl Iterator<Item = &OutPoint> {
+        self.inputs.iter().map(|input| &input.previous_output)
+    }
+
+    /// Whether any input spends `outpoint`.
+    pub fn spends(&self, outpoint: &OutPoint) -> bool {
+        self.inputs
+            .iter()
+            .any(|input| input.previous_output == *outpoint)
+    }
+
+    /// Whether any input carries a scriptSig or witness. An unsigned template,
+    /// as built before signing, has neither on every input.
+    pub fn is_signed(&self) -> bool {
+        self.inputs
+            .iter()
+            .any(|input| !input.script_sig.is_empty() || !input.witness.is_empty())
+    }
+
+    /// Compares two transactions field by field, ignoring witness stacks. Two
+    /// signings of the same segwit transaction compare equal here.
+    pub fn eq_ignoring_witness(&self, other: &BitcoinTransaction) -> bool {
+        self.version == other.version
+            && self.lock_time == other.lock_time
+            && self.outputs == other.outputs
+            && self.inputs.len() == other.inputs.len()
+            && self.inputs.iter().zip(&other.inputs).all(|(a, b)| {
+                a.previous_output == b.previous_output
+                    && a.script_sig == b.script_sig
+                    && a.sequence == b.sequence
+            })
+    }
+
+    /// Applies `f` to the input at index `i`, returning `InvalidFormat` if
+    /// there is no such input.
+    pub fn map_input(
+        &mut self,
+        i: usize,
+        f: impl FnOnce(&mut TransactionInput),
+    ) -> Result<(), BitcoinError> {
+        let input = self.inputs.get_mut(i).ok_or(BitcoinError::InvalidFormat)?;
+        f(input);
+        Ok(())
+    }
+
+    pub fn replace_output(
+        &mut self,
+        index: usize,
+        output: TransactionOutput,
+    ) -> Result<(), BitcoinError> {
+        let slot = self
+            .outputs
+            .get_mut(index)
+            .ok_or(BitcoinError::InvalidFormat)?;
+        *slot = output;
+        Ok(())
+    }
+
+    pub fn remove_output(&mut self, index: usize) -> Result<TransactionOutput, BitcoinError> {
+        if index >= self.outputs.len() {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        Ok(self.outputs.remove(index))
+    }
+
+    /// Inserts `output` at `index`, shifting later outputs along. `index` may
+    /// equal the output count to append.
+    pub fn add_output_at(
+        &mut self,
+        index: usize,
+        output: TransactionOutput,
+    ) -> Result<(), BitcoinError> {
+        if index > self.outputs.len() {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        self.outputs.insert(index, output);
+        Ok(())
+    }
+
+    pub fn witness(&self, input_index: usize) -> Option<&Witness> {
+        self.inputs.get(input_index).map(|input| &input.witness)
+    }
+
+    pub fn witness_mut(&mut self, input_index: usize) -> Option<&mut Witness> {
+        self.inputs
+            .get_mut(input_index)
+            .map(|input| &mut input.witness)
+    }
+
+    /// The transaction id: double SHA-256 of the serialization without
+    /// witness data, in internal byte order.
+    pub fn txid(&self) -> Txid {
+        Txid(double_sha256(&self.to_bytes_without_witness()))
+    }
+
+    /// `txid` computed by hashing the serialization as it is produced,
+    /// without buffering the whole transaction.
+    pub fn txid_streaming(&self) -> Txid {
+        let mut writer = hashes::HashWriter::new();
+        self.to_writer_without_witness(&mut writer)
+            .expect("hashing cannot fail");
+        Txid(writer.finalize_double())
+    }
+
+    /// `txid` computed with a caller-supplied SHA-256 engine.
+    pub fn txid_with<E: hashes::Sha256Engine>(&self) -> Txid {
+        Txid(hashes::double_sha256_with::<E>(
+            &self.to_bytes_without_witness(),
+        ))
+    }
+
+    /// The witness transaction id: double SHA-256 of the full serialization.
+    /// Equal to `txid` for transactions without witness data.
+    pub fn wtxid(&self) -> Txid {
+        Txid(double_sha256(&self.to_bytes()))
+    }
+
+    /// Pairs each output with the outpoint that would spend it, in output
+    /// order. The txid is computed once up front.
+    pub fn iter_outputs_with_outpoints(
+        &self,
+    ) -> impl Iterator<Item = (OutPoint, &TransactionOutput)> {
+        let txid = self.txid();
+        self.outputs.iter().enumerate().map(move |(vout, output)| {
+            let outpoint = OutPoint {
+                txid: txid.clone(),
+                vout: vout as u32,
+            };
+            (outpoint, output)
+        })
+    }
+
+    /// BIP141 weight: non-witness bytes count four times, witness bytes once.
+    pub fn weight(&self) -> usize {
+        let base_size = self.serialized_len_with(false);
+        let total_size = self.serialized_len();
+        base_size * 3 + total_size
+    }
+
+    pub fn vsize(&self) -> usize {
+        self.weight().div_ceil(4)
+    }
+
+    /// A one-line `<txid> v<version> in=<n> out=<m> <vsize>vB` description for
+    /// logs. `Display` gives the full dump.
+    pub fn summary(&self) -> String {
+        format!(
+            "{} v{} in={} out={} {}vB",
+            self.txid().to_display_string(),
+            self.version,
+            self.inputs.len(),
+            self.outputs.len(),
+            self.vsize()
+        )
+    }
+
+    /// Estimates the virtual size this transaction will have once signed, with
+    /// `input_types[i]` describing the scriptSig and witness that input `i` is
+    /// expected to carry. Inputs without an entry in `input_types` are counted
+    /// with the scriptSig and witness they currently have.
+    pub fn estimated_vsize(&self, input_types: &[InputKind]) -> usize {
+        let mut base_size = 4 + 4;
+        base_size += CompactSize::new(self.inputs.len() as u64).serialized_len();
+        base_size += CompactSize::new(self.outputs.len() as u64).serialized_len();
+        base_size += self
+            .outputs
+            .iter()
+            .map(TransactionOutput::serialized_len)
+            .sum::<usize>();
+
+        let mut witness_sizes = Vec::with_capacity(self.inputs.len());
+        for (i, input) in self.inputs.iter().enumerate() {
+            let (script_sig_len, witness_len) = match input_types.get(i) {
+                Some(kind) => (kind.script_sig_len(), kind.witness_len()),
+                None if input.witness.is_empty() => (input.script_sig.len(), 0),
+                None => (input.script_sig.len(), input.witness.serialized_len()),
+            };
+            base_size += 36 + 4 + script_sig_len;
+            base_size += CompactSize::new(script_sig_len as u64).serialized_len();
+            witness_sizes.push(witness_len);
+        }
+
+        let mut weight = base_size * 4;
+        if witness_sizes.iter().any(|&len| len > 0) {
+            // Marker and flag, plus an empty stack for every non-witness input.
+            weight += 2;
+            weight += witness_sizes.iter().map(|&len| len.max(1)).sum::<usize>();
+        }
+        weight.div_ceil(4)
+    }
+
+    /// Serializes the full transaction. When any input carries witness data the
+    /// BIP144 layout is used (marker `0x00`, flag `0x01`, witnesses after the
+    /// outputs); otherwise the legacy layout is produced. That includes a
+    /// transaction with no inputs, whose bytes only round-trip through
+    /// `from_bytes_without_witness`.
     pub fn to_bytes(&self) -> Vec<u8> {
-        let mut b = self.version.to_le_bytes().to_vec();
-        b.extend_from_slice(&CompactSize::new(self.inputs.len() as u64).to_bytes());
-        for input in &self.inputs {
-            b.extend_from_slice(&input.to_bytes());
+        let mut b = Vec::new();
+        self.encode_into(&mut b);
+        b
+    }
+
+    /// The length of `to_bytes`, computed from the field sizes without
+    /// serializing.
+    pub fn serialized_len(&self) -> usize {
+        self.serialized_len_with(self.has_witness())
+    }
+
+    fn serialized_len_with(&self, segwit: bool) -> usize {
+        let mut len = 4 + 4;
+        len += CompactSize::new(self.inputs.len() as u64).serialized_len();
+        len += self
+            .inputs
+            .iter()
+            .map(TransactionInput::serialized_len)
+            .sum::<usize>();
+        len += CompactSize::new(self.outputs.len() as u64).serialized_len();
+        len += self
+            .outputs
+            .iter()
+            .map(TransactionOutput::serialized_len)
+            .sum::<usize>();
+        if segwit {
+            len += 2;
+            len += self
+                .inputs
+                .iter()
+                .map(|input| input.witness.serialized_len())
+                .sum::<usize>();
         }
-        b.extend_from_slice(&self.lock_time.to_le_bytes());
+        len
+    }
+
+    /// Appends the `to_bytes` serialization to `buf` and returns the number of
+    /// bytes written, so one buffer can be reused across many transactions.
+    pub fn encode_into(&self, buf: &mut Vec<u8>) -> usize {
+        let start = buf.len();
+        self.to_writer(buf).expect("writing to a Vec cannot fail");
+        buf.len() - start
+    }
+
+    /// Streams the `to_bytes` serialization into `writer`.
+    pub fn to_writer<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        self.write_to(writer, self.has_witness())
+    }
+
+    /// Streams the `to_bytes_without_witness` serialization into `writer`.
+    pub fn to_writer_without_witness<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        self.write_to(writer, false)
+    }
+
+    /// Serializes the transaction in the legacy layout, dropping any witness
+    /// data. This is the encoding committed to by the txid.
+    pub fn to_bytes_without_witness(&self) -> Vec<u8> {
+        let mut b = Vec::new();
+        self.to_writer_without_witness(&mut b)
+            .expect("writing to a Vec cannot fail");
         b
     }
 
+    /// The legacy layout with every scriptSig replaced by an empty script and
+    /// no witness data, which shows what a txid would commit to without the
+    /// malleable signature scripts.
+    pub fn to_bytes_no_scripts(&self) -> Vec<u8> {
+        let mut blanked = self.clone();
+        for input in &mut blanked.inputs {
+            input.script_sig = Script::new(Vec::new());
+        }
+        blanked.to_bytes_without_witness()
+    }
+
+    /// Double SHA-256 of `to_bytes_no_scripts`: the version, outpoints,
+    /// sequences, outputs and lock time. Every signing of the same template
+    /// shares this fingerprint.
+    pub fn normalized_fingerprint(&self) -> [u8; 32] {
+        double_sha256(&self.to_bytes_no_scripts())
+    }
+
+    fn write_to<W: io::Write>(&self, w: &mut W, segwit: bool) -> io::Result<()> {
+        w.write_all(&self.version.to_le_bytes())?;
+        if segwit {
+            w.write_all(&[0x00, 0x01])?;
+        }
+        w.write_all(&CompactSize::new(self.inputs.len() as u64).to_bytes())?;
+        for input in &self.inputs {
+            w.write_all(&input.to_bytes())?;
+        }
+        w.write_all(&CompactSize::new(self.outputs.len() as u64).to_bytes())?;
+        for output in &self.outputs {
+            w.write_all(&output.to_bytes())?;
+        }
+        if segwit {
+            for input in &self.inputs {
+                w.write_all(&input.witness.to_bytes())?;
+            }
+        }
+        w.write_all(&self.lock_time.to_le_bytes())
+    }
+
+    /// Parses a complete transaction: inputs, outputs and, when the BIP144
+    /// marker and flag are present, the per-input witnesses.
+    ///
+    /// A transaction with no inputs and at least one output is ambiguous: its
+    /// legacy encoding starts with the same `0x00` that marks a segwit one, so
+    /// this parser misreads it. Use `from_bytes_without_witness` for such
+    /// transactions.
     pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        Self::from_bytes_with_config(bytes, &ParseConfig::default())
+    }
+
+    /// Whether `original`, the bytes this transaction was parsed from, is
+    /// exactly what `to_bytes` produces. The parsers accept some encodings
+    /// that do not round-trip, such as a CompactSize written wider than
+    /// necessary; this catches them.
+    pub fn is_canonically_encoded(&self, original: &[u8]) -> bool {
+        self.to_bytes() == original
+    }
+
+    /// Parses `bytes` as exactly one transaction under the strictest rules
+    /// available: the strict `ParseConfig` limits, no trailing bytes, an
+    /// encoding `to_bytes` reproduces exactly, minimal pushes in every
+    /// non-coinbase scriptSig, and the context-free consensus checks (at
+    /// least one input and output, no output or total above `MAX_MONEY`, no
+    /// outpoint spent twice). Any failure is `InvalidFormat`, apart from
+    /// truncated or oversized input.
+    pub fn from_consensus_bytes(bytes: &[u8]) -> Result<Self, BitcoinError> {
+        let (tx, consumed) = Self::from_bytes_with_config(bytes, &ParseConfig::strict())?;
+        if consumed != bytes.len() || !tx.is_canonically_encoded(bytes) {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        if tx.inputs.is_empty() || tx.outputs.is_empty() {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        // A coinbase scriptSig is arbitrary data rather than a script.
+        if tx
+            .inputs
+            .iter()
+            .any(|input| !input.previous_output.is_null() && !input.script_sig.has_minimal_pushes())
+        {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        let mut total = Amount::ZERO;
+        for output in &tx.outputs {
+            total = total
+                .checked_add(Amount::from_sat(output.value))
+                .filter(|&total| total <= Amount::MAX_MONEY)
+                .ok_or(BitcoinError::InvalidFormat)?;
+        }
+        let mut spent = BTreeSet::new();
+        if !tx
+            .inputs
+            .iter()
+            .all(|input| spent.insert(&input.previous_output))
+        {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        Ok(tx)
+    }
+
+    /// Parses either serialization and reports whether the BIP144 segwit
+    /// layout was used, so the caller can re-encode in the same format.
+    /// Trailing bytes are ignored, as with `from_bytes`.
+    pub fn from_bytes_detect(bytes: &[u8]) -> Result<(Self, bool), BitcoinError> {
+        let (tx, _) = Self::from_bytes(bytes)?;
+        // The parser rejects a segwit layout whose witnesses are all empty, so
+        // a parsed witness means the input was segwit.
+        let had_witness = tx.has_witness();
+        Ok((tx, had_witness))
+    }
+
+    /// Parses the legacy layout only, the inverse of `to_bytes_without_witness`.
+    pub fn from_bytes_without_witness(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        let config = ParseConfig {
+            allow_witness: false,
+            ..ParseConfig::default()
+        };
+        Self::from_bytes_with_config(bytes, &config)
+    }
+
+    pub fn from_bytes_with_config(
+        bytes: &[u8],
+        config: &ParseConfig,
+    ) -> Result<(Self, usize), BitcoinError> {
+        // Parse within the size limit only. Running out of bytes there while
+        // the caller's buffer continues means the transaction is too large.
+        if bytes.len() > config.max_tx_size {
+            return match Self::parse(&bytes[..config.max_tx_size], config) {
+                Err(BitcoinError::InsufficientBytes) => Err(BitcoinError::OversizedTransaction),
+                result => result,
+            };
+        }
+        Self::parse(bytes, config)
+    }
+
+    fn parse(bytes: &[u8], config: &ParseConfig) -> Result<(Self, usize), BitcoinError> {
         if bytes.len() < 4 {
             return Err(BitcoinError::InsufficientBytes);
         }
         let version = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
-        let (size, offset1) = CompactSize::from_bytes(&bytes[4..])?;
+        let mut offset = 4;
+        let segwit =
+            config.allow_witness && bytes.len() > 5 && bytes[4] == 0x00 && bytes[5] != 0x00;
+        if segwit {
+            if bytes[5] != 0x01 {
+                return Err(BitcoinError::InvalidFormat);
+            }
+            offset += 2;
+        }
+        let (mut inputs, inputs_len) = Self::read_inputs(rest(bytes, offset)?)?;
+        offset = advance(offset, inputs_len)?;
+        let (count, count_len) = CompactSize::from_bytes(rest(bytes, offset)?)?;
+        offset = advance(offset, count_len)?;
+        let mut outputs = Vec::new();
+        for _ in 0..*count {
+            let (output, output_len) = TransactionOutput::from_bytes(rest(bytes, offset)?)?;
+            outputs.push(output);
+            offset = advance(offset, output_len)?;
+        }
+        if segwit {
+            for input in &mut inputs {
+                let (witness, witness_len) =
+                    Witness::from_bytes_with_config(rest(bytes, offset)?, config)?;
+                input.witness = witness;
+                offset = advance(offset, witness_len)?;
+            }
+            // A segwit serialization with only empty witnesses must use the
+            // legacy layout instead.
+            if inputs.iter().all(|input| input.witness.is_empty()) {
+                return Err(BitcoinError::InvalidFormat);
+            }
+        }
+        let lock_time = Self::read_lock_time(bytes, offset)?;
+        Ok((
+            BitcoinTransaction {
+                version,
+                inputs,
+                outputs,
+                lock_time,
+            },
+            advance(offset, 4)?,
+        ))
+    }
+
+    fn read_inputs(bytes: &[u8]) -> Result<(Vec<TransactionInput>, usize), BitcoinError> {
+        let (size, mut offset) = CompactSize::from_bytes(bytes)?;
         let mut inputs = Vec::new();
-        let mut offset = 4 + offset1;
-        for _ in 0..size.value {
-            let (input, input_len) = TransactionInput::from_bytes(&bytes[offset..])?;
+        for _ in 0..*size {
+            let (input, input_len) = TransactionInput::from_bytes(rest(bytes, offset)?)?;
             inputs.push(input);
-            offset += input_len;
+            offset = advance(offset, input_len)?;
+        }
+        Ok((inputs, offset))
+    }
+
+    fn read_lock_time(bytes: &[u8], offset: usize) -> Result<u32, BitcoinError> {
+        let lock_time = bytes
+            .get(offset..advance(offset, 4)?)
+            .ok_or(BitcoinError::InsufficientBytes)?;
+        Ok(u32::from_le_bytes([
+            lock_time[0],
+            lock_time[1],
+            lock_time[2],
+            lock_time[3],
+        ]))
+    }
+}
+
+/// The expected spend shape of an input, used to size a transaction before it
+/// is signed. Sizes assume a 72-byte DER signature (including the sighash
+/// byte) and a 33-byte compressed public key, so estimates err on the high side.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum InputKind {
+    P2pkh,
+    P2wpkh,
+    P2shP2wpkh,
+    P2trKeyPath,
+    Custom {
+        script_sig_len: usize,
+        witness_len: usize,
+    },
+}
+
+impl InputKind {
+    pub fn script_sig_len(&self) -> usize {
+        match self {
+            // <sig> <pubkey>
+            InputKind::P2pkh => 1 + 72 + 1 + 33,
+            InputKind::P2wpkh | InputKind::P2trKeyPath => 0,
+            // <OP_0 <20-byte hash>>
+            InputKind::P2shP2wpkh => 1 + 22,
+            InputKind::Custom { script_sig_len, .. } => *script_sig_len,
+        }
+    }
+
+    /// Serialized witness size in weight units, including the item count.
+    /// Zero means the input has no witness.
+    pub fn witness_len(&self) -> usize {
+        match self {
+            InputKind::P2pkh => 0,
+            InputKind::P2wpkh | InputKind::P2shP2wpkh => 1 + 1 + 72 + 1 + 33,
+            InputKind::P2trKeyPath => 1 + 1 + 64,
+            InputKind::Custom { witness_len, .. } => *witness_len,
         }
-        if bytes.len() < offset + 4 {
+    }
+}
+
+// Legacy inputs-only encoding.
+//
+// The week three exercises model a transaction as `version || inputs ||
+// lock_time`, with no outputs and no witness. That layout is not a valid
+// Bitcoin serialization, but it is kept here, separate from `to_bytes` and
+// `from_bytes`, so the exercise material keeps working against it.
+impl BitcoinTransaction {
+    /// Serializes only the version, the inputs and the lock time. Outputs and
+    /// witnesses are ignored.
+    pub fn to_bytes_inputs_only(&self) -> Vec<u8> {
+        let mut b = self.version.to_le_bytes().to_vec();
+        b.extend_from_slice(&CompactSize::new(self.inputs.len() as u64).to_bytes());
+        for input in &self.inputs {
+            b.extend_from_slice(&input.to_bytes());
+        }
+        b.extend_from_slice(&self.lock_time.to_le_bytes());
+        b
+    }
+
+    /// Parses the exercise layout `version || inputs || lock_time`. The
+    /// returned transaction has no outputs. Use `from_bytes` for real
+    /// transactions.
+    pub fn from_bytes_inputs_only(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        if bytes.len() < 4 {
             return Err(BitcoinError::InsufficientBytes);
         }
-        let lock_time = u32::from_le_bytes([
-            bytes[offset],
-            bytes[offset + 1],
-            bytes[offset + 2],
-            bytes[offset + 3],
-        ]);
+        let version = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        let (inputs, inputs_len) = Self::read_inputs(rest(bytes, 4)?)?;
+        let offset = advance(4, inputs_len)?;
+        let lock_time = Self::read_lock_time(bytes, offset)?;
         Ok((
             BitcoinTransaction {
                 version,
                 inputs,
+                outputs: Vec::new(),
                 lock_time,
             },
-            offset + 4,
+            advance(offset, 4)?,
         ))
     }
 }
@@ -287,8 +1367,8 @@ impl fmt::Display for BitcoinTransaction {
             writeln!(f, "Input[{}]:", i)?;
             writeln!(
                 f,
-                "  Previous Output Txid: {:x?}",
-                input.previous_output.txid.0
+                "  Previous Output Txid: {}",
+                input.previous_output.txid.to_display_string()
             )?;
             writeln!(f, "  Previous Output Vout: {}", input.previous_output.vout)?;
             writeln!(
@@ -298,6 +1378,19 @@ impl fmt::Display for BitcoinTransaction {
                 input.script_sig.bytes
             )?;
             writeln!(f, "  Sequence: {}", input.sequence)?;
+            if !input.witness.is_empty() {
+                writeln!(f, "  Witness: {} items", input.witness.items.len())?;
+            }
+        }
+        for (i, output) in self.outputs.iter().enumerate() {
+            writeln!(f, "Output[{}]:", i)?;
+            writeln!(f, "  Value: {}", output.value)?;
+            writeln!(
+                f,
+                "  ScriptPubKey ({} bytes): {:x?}",
+                output.script_pubkey.bytes.len(),
+                output.script_pubkey.bytes
+            )?;
         }
         writeln!(f, "Lock Time: {}", self.lock_time)
     }
@@ -0,0 +1,111 @@
+use crate::{BitcoinTransaction, TransactionInput};
+
+/// Lock times below this are block heights; from it up they are Unix times.
+pub const LOCKTIME_THRESHOLD: u32 = 500_000_000;
+
+/// BIP68: when set in a sequence, the relative lock counts 512-second units
+/// rather than blocks.
+pub const SEQUENCE_LOCKTIME_TYPE_FLAG: u32 = 1 << 22;
+
+/// Mask for the relative lock value in a sequence.
+pub const SEQUENCE_LOCKTIME_MASK: u32 = 0x0000_FFFF;
+
+/// How the absolute `lock_time` of a transaction is interpreted.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum LockTimeKind {
+    /// Zero, or ignored because every input has a final sequence.
+    Disabled,
+    /// Minable in a block at a height above this.
+    Height(u32),
+    /// Minable once the median time past is above this.
+    Time(u32),
+}
+
+/// A BIP68 relative lock, counted from the confirmation of the spent output.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum RelativeLockTime {
+    Blocks(u16),
+    Seconds(u32),
+}
+
+/// What stands between a transaction and being mined, from
+/// `earliest_spendable`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Spendability {
+    /// The absolute lock time still to be reached, or `None` once it is met.
+    pub pending_lock_time: Option<LockTimeKind>,
+    /// The most confirmations any input's relative lock needs on its prevout.
+    pub relative_blocks: u16,
+    /// The longest time any input's relative lock needs since its prevout
+    /// confirmed, in seconds.
+    pub relative_seconds: u32,
+}
+
+impl Spendability {
+    /// Whether the transaction could go in the next block. Relative locks
+    /// depend on when each prevout confirmed, which is not known here, so
+    /// any non-zero one counts as unmet.
+    pub fn is_spendable_now(&self) -> bool {
+        self.pending_lock_time.is_none() && self.relative_blocks == 0 && self.relative_seconds == 0
+    }
+}
+
+impl TransactionInput {
+    /// The relative lock this input's sequence encodes, or `None` when the
+    /// disable flag is set. Only enforced in version 2 or later transactions.
+    pub fn relative_locktime(&self) -> Option<RelativeLockTime> {
+        if !self.has_relative_lock_time() {
+            return None;
+        }
+        let value = self.sequence & SEQUENCE_LOCKTIME_MASK;
+        if self.sequence & SEQUENCE_LOCKTIME_TYPE_FLAG != 0 {
+            Some(RelativeLockTime::Seconds(value * 512))
+        } else {
+            Some(RelativeLockTime::Blocks(value as u16))
+        }
+    }
+}
+
+impl BitcoinTransaction {
+    pub fn lock_time_kind(&self) -> LockTimeKind {
+        match self.lock_time {
+            _ if self.lock_time == 0 || self.is_final() => LockTimeKind::Disabled,
+            height if height < LOCKTIME_THRESHOLD => LockTimeKind::Height(height),
+            time => LockTimeKind::Time(time),
+        }
+    }
+
+    /// Combines the absolute lock time, checked against the next block after
+    /// `current_height` and the median time past `current_time`, with the
+    /// largest relative lock among the inputs.
+    pub fn earliest_spendable(&self, current_height: u32, current_time: u32) -> Spendability {
+        let kind = self.lock_time_kind();
+        let pending_lock_time = match kind {
+            LockTimeKind::Height(height) if height > current_height => Some(kind),
+            LockTimeKind::Time(time) if time >= current_time => Some(kind),
+            _ => None,
+        };
+        let mut spendability = Spendability {
+            pending_lock_time,
+            relative_blocks: 0,
+            relative_seconds: 0,
+        };
+        if self.enforces_bip68() {
+            for lock in self
+                .inputs
+                .iter()
+                .filter_map(TransactionInput::relative_locktime)
+            {
+                match lock {
+                    RelativeLockTime::Blocks(blocks) => {
+                        spendability.relative_blocks = spendability.relative_blocks.max(blocks)
+                    }
+                    RelativeLockTime::Seconds(seconds) => {
+                        spendability.relative_seconds = spendability.relative_seconds.max(seconds)
+                    }
+                }
+            }
+        }
+        spendability
+    }
+}
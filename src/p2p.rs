@@ -0,0 +1,358 @@
+use crate::block::BlockHeader;
+use crate::{BitcoinError, CompactSize, advance, read_var_bytes, rest, write_var_bytes};
+
+fn read_array<const N: usize>(bytes: &[u8], offset: usize) -> Result<[u8; N], BitcoinError> {
+    rest(bytes, offset)?
+        .get(..N)
+        .and_then(|slice| slice.try_into().ok())
+        .ok_or(BitcoinError::InsufficientBytes)
+}
+
+fn read_var_str(bytes: &[u8]) -> Result<(String, usize), BitcoinError> {
+    let (raw, len) = read_var_bytes(bytes)?;
+    let s = String::from_utf8(raw).map_err(|_| BitcoinError::InvalidFormat)?;
+    Ok((s, len))
+}
+
+fn write_hashes(b: &mut Vec<u8>, hashes: &[[u8; 32]]) {
+    b.extend_from_slice(&CompactSize::new(hashes.len() as u64).to_bytes());
+    for hash in hashes {
+        b.extend_from_slice(hash);
+    }
+}
+
+/// Reads a CompactSize count followed by that many 32-byte hashes, starting
+/// at `offset`. Returns the hashes and the offset just past them.
+fn read_hashes(bytes: &[u8], offset: usize) -> Result<(Vec<[u8; 32]>, usize), BitcoinError> {
+    let (count, count_len) = CompactSize::from_bytes(rest(bytes, offset)?)?;
+    let mut offset = advance(offset, count_len)?;
+    let mut hashes = Vec::new();
+    for _ in 0..*count {
+        hashes.push(read_array(bytes, offset)?);
+        offset = advance(offset, 32)?;
+    }
+    Ok((hashes, offset))
+}
+
+/// Payload of the `headers` message. On the wire every header is followed by a
+/// transaction count, which is always `0x00` here.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct HeadersMessage {
+    pub headers: Vec<BlockHeader>,
+}
+
+impl HeadersMessage {
+    pub fn new(headers: Vec<BlockHeader>) -> Self {
+        HeadersMessage { headers }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut b = CompactSize::new(self.headers.len() as u64).to_bytes();
+        for header in &self.headers {
+            b.extend_from_slice(&header.to_bytes());
+            b.push(0x00);
+        }
+        b
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        let (count, mut offset) = CompactSize::from_bytes(bytes)?;
+        let mut headers = Vec::new();
+        for _ in 0..*count {
+            let (header, header_len) = BlockHeader::from_bytes(rest(bytes, offset)?)?;
+            offset = advance(offset, header_len)?;
+            match bytes.get(offset) {
+                Some(0x00) => offset = advance(offset, 1)?,
+                Some(_) => return Err(BitcoinError::InvalidFormat),
+                None => return Err(BitcoinError::InsufficientBytes),
+            }
+            headers.push(header);
+        }
+        Ok((HeadersMessage { headers }, offset))
+    }
+}
+
+/// A network address as carried in `version` messages: services, an IPv6 (or
+/// IPv4-mapped) address and a big-endian port.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct NetAddress {
+    pub services: u64,
+    pub ip: [u8; 16],
+    pub port: u16,
+}
+
+impl NetAddress {
+    pub const SIZE: usize = 26;
+
+    pub fn new(services: u64, ip: [u8; 16], port: u16) -> Self {
+        NetAddress { services, ip, port }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut b = self.services.to_le_bytes().to_vec();
+        b.extend_from_slice(&self.ip);
+        b.extend_from_slice(&self.port.to_be_bytes());
+        b
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        Ok((
+            NetAddress {
+                services: u64::from_le_bytes(read_array(bytes, 0)?),
+                ip: read_array(bytes, 8)?,
+                port: u16::from_be_bytes(read_array(bytes, 24)?),
+            },
+            Self::SIZE,
+        ))
+    }
+}
+
+/// Payload of the `addr` message: known peers, each with the time it was
+/// last seen.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct AddrMessage {
+    pub addresses: Vec<(u32, NetAddress)>,
+}
+
+impl AddrMessage {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut b = CompactSize::new(self.addresses.len() as u64).to_bytes();
+        for (timestamp, address) in &self.addresses {
+            b.extend_from_slice(&timestamp.to_le_bytes());
+            b.extend_from_slice(&address.to_bytes());
+        }
+        b
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        let (count, mut offset) = CompactSize::from_bytes(bytes)?;
+        let mut addresses = Vec::new();
+        for _ in 0..*count {
+            let timestamp = u32::from_le_bytes(read_array(bytes, offset)?);
+            offset = advance(offset, 4)?;
+            let (address, len) = NetAddress::from_bytes(rest(bytes, offset)?)?;
+            addresses.push((timestamp, address));
+            offset = advance(offset, len)?;
+        }
+        Ok((AddrMessage { addresses }, offset))
+    }
+}
+
+/// Payload of the `version` message that opens the handshake.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct VersionMessage {
+    pub version: i32,
+    pub services: u64,
+    pub timestamp: i64,
+    pub addr_recv: NetAddress,
+    pub addr_from: NetAddress,
+    pub nonce: u64,
+    pub user_agent: String,
+    pub start_height: i32,
+    pub relay: bool,
+}
+
+impl VersionMessage {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut b = self.version.to_le_bytes().to_vec();
+        b.extend_from_slice(&self.services.to_le_bytes());
+        b.extend_from_slice(&self.timestamp.to_le_bytes());
+        b.extend_from_slice(&self.addr_recv.to_bytes());
+        b.extend_from_slice(&self.addr_from.to_bytes());
+        b.extend_from_slice(&self.nonce.to_le_bytes());
+        b.extend_from_slice(&write_var_bytes(self.user_agent.as_bytes()));
+        b.extend_from_slice(&self.start_height.to_le_bytes());
+        b.push(self.relay as u8);
+        b
+    }
+
+    /// Parses a `version` payload. Peers older than protocol 70001 omit the
+    /// trailing relay flag, in which case it defaults to `true`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        let version = i32::from_le_bytes(read_array(bytes, 0)?);
+        let services = u64::from_le_bytes(read_array(bytes, 4)?);
+        let timestamp = i64::from_le_bytes(read_array(bytes, 12)?);
+        let mut offset = 20;
+        let (addr_recv, len) = NetAddress::from_bytes(rest(bytes, offset)?)?;
+        offset = advance(offset, len)?;
+        let (addr_from, len) = NetAddress::from_bytes(rest(bytes, offset)?)?;
+        offset = advance(offset, len)?;
+        let nonce = u64::from_le_bytes(read_array(bytes, offset)?);
+        offset = advance(offset, 8)?;
+        let (user_agent, len) = read_var_str(rest(bytes, offset)?)?;
+        offset = advance(offset, len)?;
+        let start_height = i32::from_le_bytes(read_array(bytes, offset)?);
+        offset = advance(offset, 4)?;
+        let relay = match bytes.get(offset) {
+            Some(&flag) => {
+                offset = advance(offset, 1)?;
+                flag != 0
+            }
+            None => true,
+        };
+        Ok((
+            VersionMessage {
+                version,
+                services,
+                timestamp,
+                addr_recv,
+                addr_from,
+                nonce,
+                user_agent,
+                start_height,
+                relay,
+            },
+            offset,
+        ))
+    }
+}
+
+/// Payload of the BIP157 `cfilter` message: one block's compact filter. The
+/// filter itself is kept as raw GCS-encoded bytes.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct CFilterMessage {
+    pub filter_type: u8,
+    pub block_hash: [u8; 32],
+    pub filter: Vec<u8>,
+}
+
+impl CFilterMessage {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut b = vec![self.filter_type];
+        b.extend_from_slice(&self.block_hash);
+        b.extend_from_slice(&write_var_bytes(&self.filter));
+        b
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        let [filter_type] = read_array(bytes, 0)?;
+        let block_hash = read_array(bytes, 1)?;
+        let (filter, len) = read_var_bytes(rest(bytes, 33)?)?;
+        Ok((
+            CFilterMessage {
+                filter_type,
+                block_hash,
+                filter,
+            },
+            advance(33, len)?,
+        ))
+    }
+}
+
+/// Payload of the BIP157 `cfheaders` message: the filter hashes for a range of
+/// blocks ending at `stop_hash`, plus the filter header preceding the range.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct CFHeadersMessage {
+    pub filter_type: u8,
+    pub stop_hash: [u8; 32],
+    pub previous_filter_header: [u8; 32],
+    pub filter_hashes: Vec<[u8; 32]>,
+}
+
+impl CFHeadersMessage {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut b = vec![self.filter_type];
+        b.extend_from_slice(&self.stop_hash);
+        b.extend_from_slice(&self.previous_filter_header);
+        write_hashes(&mut b, &self.filter_hashes);
+        b
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        let [filter_type] = read_array(bytes, 0)?;
+        let stop_hash = read_array(bytes, 1)?;
+        let previous_filter_header = read_array(bytes, 33)?;
+        let (filter_hashes, offset) = read_hashes(bytes, 65)?;
+        Ok((
+            CFHeadersMessage {
+                filter_type,
+                stop_hash,
+                previous_filter_header,
+                filter_hashes,
+            },
+            offset,
+        ))
+    }
+}
+
+/// Payload shared by the `getheaders` and `getblocks` messages: a block
+/// locator of hashes from the tip backwards, and the hash to stop at (all
+/// zeros for as many as the peer will send).
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct BlockLocatorMessage {
+    pub version: u32,
+    pub locator_hashes: Vec<[u8; 32]>,
+    pub stop_hash: [u8; 32],
+}
+
+impl BlockLocatorMessage {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut b = self.version.to_le_bytes().to_vec();
+        write_hashes(&mut b, &self.locator_hashes);
+        b.extend_from_slice(&self.stop_hash);
+        b
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        let version = u32::from_le_bytes(read_array(bytes, 0)?);
+        let (locator_hashes, offset) = read_hashes(bytes, 4)?;
+        let stop_hash = read_array(bytes, offset)?;
+        Ok((
+            BlockLocatorMessage {
+                version,
+                locator_hashes,
+                stop_hash,
+            },
+            advance(offset, 32)?,
+        ))
+    }
+}
+
+/// Payload of the deprecated `reject` message: the command that was rejected,
+/// a BIP61 code, a human-readable reason and, for `tx` and `block`, the hash
+/// of the rejected object.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct RejectMessage {
+    pub message: String,
+    pub ccode: u8,
+    pub reason: String,
+    pub data: Option<[u8; 32]>,
+}
+
+impl RejectMessage {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut b = write_var_bytes(self.message.as_bytes());
+        b.push(self.ccode);
+        b.extend_from_slice(&write_var_bytes(self.reason.as_bytes()));
+        if let Some(hash) = &self.data {
+            b.extend_from_slice(hash);
+        }
+        b
+    }
+
+    /// Parses a `reject` payload. The hash is optional and only read when
+    /// bytes remain after the reason, so `bytes` must be exactly the payload.
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        let (message, mut offset) = read_var_str(bytes)?;
+        let [ccode] = read_array(bytes, offset)?;
+        offset = advance(offset, 1)?;
+        let (reason, len) = read_var_str(rest(bytes, offset)?)?;
+        offset = advance(offset, len)?;
+        let data = if offset < bytes.len() {
+            let hash = read_array(bytes, offset)?;
+            offset = advance(offset, 32)?;
+            Some(hash)
+        } else {
+            None
+        };
+        Ok((
+            RejectMessage {
+                message,
+                ccode,
+                reason,
+                data,
+            },
+            offset,
+        ))
+    }
+}
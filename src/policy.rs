@@ -0,0 +1,335 @@
+use crate::amount::{Amount, FeeRate};
+use crate::script::Instruction;
+use crate::sighash::{SIGHASH_BASE_MASK, SIGHASH_SINGLE};
+use crate::{BitcoinTransaction, OutPoint, TransactionInput, TransactionOutput};
+use std::collections::HashSet;
+
+/// Default `-dustrelayfee` in Bitcoin Core.
+pub const DEFAULT_DUST_RELAY_FEE: FeeRate = FeeRate(3_000);
+
+/// Largest transaction weight relayed by default.
+pub const MAX_STANDARD_TX_WEIGHT: usize = 400_000;
+
+/// Largest scriptSig relayed by default, enough for a 15-of-15 multisig
+/// P2SH spend.
+pub const MAX_STANDARD_SCRIPTSIG_SIZE: usize = 1_650;
+
+/// Default `-bytespersigop` in Bitcoin Core: the vsize each signature
+/// operation is charged at least.
+pub const DEFAULT_BYTES_PER_SIGOP: usize = 20;
+
+/// Half the secp256k1 group order, big-endian. Standard signatures must have
+/// an S value no greater than this.
+const HALF_CURVE_ORDER: [u8; 32] = [
+    0x7F, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+    0x5D, 0x57, 0x6E, 0x73, 0x57, 0xA4, 0x50, 0x1D, 0xDF, 0xE9, 0x2F, 0x46, 0x68, 0x1B, 0x20, 0xA0,
+];
+
+/// Returns whether a DER-encoded ECDSA signature has a low S value. A trailing
+/// sighash byte after the DER sequence is allowed. Only enough of the encoding
+/// is parsed to locate S; malformed input returns `false`.
+pub fn is_low_s_signature(der: &[u8]) -> bool {
+    match der_s_value(der) {
+        Some(s) => {
+            let s = strip_leading_zeros(s);
+            if s.len() > 32 {
+                return false;
+            }
+            let mut padded = [0u8; 32];
+            padded[32 - s.len()..].copy_from_slice(s);
+            padded <= HALF_CURVE_ORDER
+        }
+        None => false,
+    }
+}
+
+fn der_s_value(der: &[u8]) -> Option<&[u8]> {
+    if der.len() < 8 || der[0] != 0x30 {
+        return None;
+    }
+    let seq_len = der[1] as usize;
+    if der.len() < 2 + seq_len {
+        return None;
+    }
+    let body = &der[2..2 + seq_len];
+    if body.len() < 2 || body[0] != 0x02 {
+        return None;
+    }
+    let r_len = body[1] as usize;
+    let s_start = 2 + r_len;
+    if body.len() < s_start + 2 || body[s_start] != 0x02 {
+        return None;
+    }
+    let s_len = body[s_start + 1] as usize;
+    if s_len == 0 || body.len() != s_start + 2 + s_len {
+        return None;
+    }
+    Some(&body[s_start + 2..])
+}
+
+fn strip_leading_zeros(bytes: &[u8]) -> &[u8] {
+    let first = bytes.iter().position(|&b| b != 0).unwrap_or(bytes.len());
+    &bytes[first..]
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum StandardnessViolation {
+    NoInputs,
+    NoOutputs,
+    DuplicateInput {
+        input_index: usize,
+    },
+    OutputValueOutOfRange,
+    WeightTooHigh {
+        weight: usize,
+    },
+    Dust {
+        output_index: usize,
+    },
+    /// The input is signed with `SIGHASH_SINGLE` but there is no output at
+    /// its index, so the signature commits to the constant 1 rather than to
+    /// any output.
+    SighashSingleWithoutOutput {
+        input_index: usize,
+    },
+    ScriptSigTooLarge {
+        input_index: usize,
+    },
+    ScriptSigNotPushOnly {
+        input_index: usize,
+    },
+}
+
+impl TransactionInput {
+    /// The sighash type of the signature this input carries: the trailing
+    /// byte of the first scriptSig push or, failing that, of the first witness
+    /// item, provided that element is a DER signature. Returns `None` when
+    /// neither holds a signature, as for a multisig scriptSig starting with
+    /// `OP_0`.
+    pub fn sighash_type(&self) -> Option<u32> {
+        let first_push = match self.script_sig.instructions().next() {
+            Some(Ok(Instruction::PushBytes(data))) => Some(data),
+            _ => None,
+        };
+        first_push
+            .filter(|data| is_signature(data))
+            .or_else(|| self.witness.get(0).filter(|item| is_signature(item)))
+            .map(|signature| signature[signature.len() - 1] as u32)
+    }
+}
+
+/// A DER signature followed by exactly one sighash byte.
+fn is_signature(data: &[u8]) -> bool {
+    der_s_value(data).is_some() && data.len() == data[1] as usize + 3
+}
+
+impl TransactionOutput {
+    /// The smallest value this output may carry before relay policy treats it
+    /// as dust: the cost of creating and later spending it at
+    /// `dust_relay_fee`, rounded up to the next satoshi as Bitcoin Core's
+    /// `GetFee` does. Provably unspendable outputs, such as `OP_RETURN` ones,
+    /// have no threshold.
+    pub fn dust_threshold(&self, dust_relay_fee: FeeRate) -> Amount {
+        if self.script_pubkey.is_provably_unspendable() {
+            return Amount::ZERO;
+        }
+        // Outpoint, scriptSig length and sequence, plus the expected unlocking
+        // data: a discounted P2WPKH witness or a P2PKH scriptSig.
+        let spend_size = if self.script_pubkey.witness_version().is_some() {
+            32 + 4 + 1 + 107 / 4 + 4
+        } else {
+            32 + 4 + 1 + 107 + 4
+        };
+        dust_relay_fee.fee_for_vsize(self.serialized_len() + spend_size)
+    }
+
+    pub fn is_dust(&self, dust_relay_fee: FeeRate) -> bool {
+        Amount(self.value) < self.dust_threshold(dust_relay_fee)
+    }
+}
+
+/// Runs the relay policy checks that only need the transaction itself and
+/// returns every violation found.
+pub fn check_standardness(
+    tx: &BitcoinTransaction,
+    dust_relay_fee: FeeRate,
+) -> Result<(), Vec<StandardnessViolation>> {
+    let mut violations = Vec::new();
+    if tx.inputs.is_empty() {
+        violations.push(StandardnessViolation::NoInputs);
+    }
+    if tx.outputs.is_empty() {
+        violations.push(StandardnessViolation::NoOutputs);
+    }
+    let mut seen = HashSet::new();
+    for (input_index, input) in tx.inputs.iter().enumerate() {
+        let key = (input.previous_output.txid.0, input.previous_output.vout);
+        if !seen.insert(key) {
+            violations.push(StandardnessViolation::DuplicateInput { input_index });
+        }
+        if !input.script_sig.scriptsig_size_ok() {
+            violations.push(StandardnessViolation::ScriptSigTooLarge { input_index });
+        }
+        if !input.script_sig.is_push_only() {
+            violations.push(StandardnessViolation::ScriptSigNotPushOnly { input_index });
+        }
+    }
+    let total = tx.outputs.iter().try_fold(Amount::ZERO, |sum, output| {
+        sum.checked_add(Amount(output.value))
+    });
+    if total.is_none_or(|total| total > Amount::MAX_MONEY) {
+        violations.push(StandardnessViolation::OutputValueOutOfRange);
+    }
+    let weight = tx.weight();
+    if weight > MAX_STANDARD_TX_WEIGHT {
+        violations.push(StandardnessViolation::WeightTooHigh { weight });
+    }
+    for (output_index, output) in tx.outputs.iter().enumerate() {
+        if output.is_dust(dust_relay_fee) {
+            violations.push(StandardnessViolation::Dust { output_index });
+        }
+    }
+    for (input_index, input) in tx.inputs.iter().enumerate().skip(tx.outputs.len()) {
+        let single = input
+            .sighash_type()
+            .is_some_and(|sighash_type| sighash_type & SIGHASH_BASE_MASK == SIGHASH_SINGLE);
+        if single {
+            violations.push(StandardnessViolation::SighashSingleWithoutOutput { input_index });
+        }
+    }
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(violations)
+    }
+}
+
+/// How a transaction's sequences interact with replacement and lock time.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum RbfPolicy {
+    /// Every sequence is `SEQUENCE_FINAL`: not replaceable and the lock time
+    /// is not enforced.
+    Final,
+    /// Some input enables the lock time but none signals replaceability.
+    NonReplaceable,
+    /// At least one input signals BIP125 replace-by-fee.
+    Replaceable,
+}
+
+/// A summary of the input sequences, as used to tell wallet software apart.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct SequenceFingerprint {
+    pub all_equal: bool,
+    pub rbf: RbfPolicy,
+    /// Whether any input carries an enforced BIP68 relative lock time.
+    pub relative_lock_times: bool,
+}
+
+impl BitcoinTransaction {
+    pub fn sequence_fingerprint(&self) -> SequenceFingerprint {
+        let all_equal = self
+            .inputs
+            .windows(2)
+            .all(|pair| pair[0].sequence == pair[1].sequence);
+        let rbf = if self.inputs.iter().any(TransactionInput::signals_rbf) {
+            RbfPolicy::Replaceable
+        } else if self.is_final() {
+            RbfPolicy::Final
+        } else {
+            RbfPolicy::NonReplaceable
+        };
+        let relative_lock_times = self.enforces_bip68()
+            && self
+                .inputs
+                .iter()
+                .any(TransactionInput::has_relative_lock_time);
+        SequenceFingerprint {
+            all_equal,
+            rbf,
+            relative_lock_times,
+        }
+    }
+}
+
+/// The first place a transaction departs from the BIP69 canonical ordering:
+/// inputs by previous txid (as displayed) then vout, outputs by value then
+/// scriptPubKey bytes.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Bip69Violation {
+    /// The input at `index` sorts before the one preceding it.
+    InputOutOfOrder { index: usize },
+    /// The output at `index` sorts before the one preceding it.
+    OutputOutOfOrder { index: usize },
+}
+
+impl BitcoinTransaction {
+    /// Reports whether the inputs and outputs are already in BIP69 order.
+    /// Inputs are checked first.
+    pub fn check_bip69(&self) -> Result<(), Bip69Violation> {
+        if let Some(index) = first_out_of_order(self.inputs.iter().map(|i| &i.previous_output)) {
+            return Err(Bip69Violation::InputOutOfOrder { index });
+        }
+        if let Some(index) = first_out_of_order(self.outputs.iter()) {
+            return Err(Bip69Violation::OutputOutOfOrder { index });
+        }
+        Ok(())
+    }
+}
+
+fn first_out_of_order<'a, T: Ord + 'a>(items: impl Iterator<Item = &'a T>) -> Option<usize> {
+    let mut previous: Option<&T> = None;
+    for (index, item) in items.enumerate() {
+        if previous.is_some_and(|previous| item < previous) {
+            return Some(index);
+        }
+        previous = Some(item);
+    }
+    None
+}
+
+impl BitcoinTransaction {
+    /// Whether `paid_fee` covers the minimum relay fee for this transaction's
+    /// weight, i.e. whether nodes with that setting will relay it.
+    pub fn meets_min_relay_fee(&self, paid_fee: Amount, min_relay_fee_rate: FeeRate) -> bool {
+        paid_fee >= min_relay_fee_rate.fee_for(self.weight())
+    }
+
+    /// The vsize the mempool ranks this transaction by when it has `sigops`
+    /// signature operations: the larger of `vsize` and `sigops` times
+    /// `DEFAULT_BYTES_PER_SIGOP`, so sigop-heavy transactions pay for them.
+    pub fn sigop_adjusted_vsize(&self, sigops: usize) -> usize {
+        self.vsize().max(sigops * DEFAULT_BYTES_PER_SIGOP)
+    }
+
+    /// Whether paying `fee_rate` on this transaction's vsize costs no more
+    /// than `budget`.
+    pub fn fits_fee_budget(&self, budget: Amount, fee_rate: FeeRate) -> bool {
+        fee_rate.fee_for_vsize(self.vsize()) <= budget
+    }
+}
+
+/// Whether `a` and `b` double-spend each other: they spend at least one
+/// outpoint in common but have different txids, so at most one can confirm.
+/// Re-signing only segwit inputs leaves the txid unchanged, so such
+/// re-signed transactions are not reported as conflicts.
+pub fn conflicts(a: &BitcoinTransaction, b: &BitcoinTransaction) -> bool {
+    let spent: HashSet<&OutPoint> = a.spent_outpoints().collect();
+    b.spent_outpoints().any(|outpoint| spent.contains(outpoint)) && a.txid() != b.txid()
+}
+
+/// Counts transactions by fee rate in sat/vB for a histogram. With `k`
+/// ascending `bucket_edges` there are `k + 1` buckets: bucket 0 holds rates
+/// below the first edge, bucket `i` rates from `bucket_edges[i - 1]` up to but
+/// excluding `bucket_edges[i]`, and the last bucket everything from the final
+/// edge up.
+pub fn fee_rate_buckets(
+    txs_with_fees: &[(&BitcoinTransaction, Amount)],
+    bucket_edges: &[f64],
+) -> Vec<usize> {
+    let mut counts = vec![0; bucket_edges.len() + 1];
+    for (tx, fee) in txs_with_fees {
+        let rate = fee.to_sat() as f64 / tx.vsize() as f64;
+        counts[bucket_edges.partition_point(|&edge| edge <= rate)] += 1;
+    }
+    counts
+}
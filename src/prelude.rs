@@ -0,0 +1,23 @@
+//! The types and traits most code needs, for a single glob import.
+//!
+//! ```
+//! use rust_week_3_exercises::prelude::*;
+//!
+//! let previous = OutPoint::new([0x11; 32], 0);
+//! let tx = TransactionBuilder::new()
+//!     .add_input(previous.clone())
+//!     .add_output(Amount::from_sat(50_000), Script::new(vec![0x00, 0x14]))
+//!     .build();
+//!
+//! let bytes = tx.consensus_encode();
+//! let (decoded, consumed) = BitcoinTransaction::consensus_decode(&bytes)?;
+//! assert_eq!(consumed, bytes.len());
+//! assert_eq!(decoded.inputs[0].previous_output, previous);
+//! # Ok::<(), BitcoinError>(())
+//! ```
+
+pub use crate::encode::{Decodable, Encodable};
+pub use crate::{
+    Amount, BitcoinError, BitcoinTransaction, CompactSize, OutPoint, Script, TransactionBuilder,
+    TransactionInput, TransactionOutput, Txid, Witness,
+};
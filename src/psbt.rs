@@ -0,0 +1,190 @@
+use crate::{
+    BitcoinError, BitcoinTransaction, Script, TransactionOutput, Witness, advance, read_var_bytes,
+    rest, write_var_bytes,
+};
+
+const MAGIC: [u8; 5] = *b"psbt\xff";
+
+const GLOBAL_UNSIGNED_TX: u8 = 0x00;
+const INPUT_NON_WITNESS_UTXO: u8 = 0x00;
+const INPUT_WITNESS_UTXO: u8 = 0x01;
+
+/// One raw key-value pair: the key (type byte followed by any key data) and
+/// the value.
+pub type KeyValue = (Vec<u8>, Vec<u8>);
+
+/// A BIP174 partially signed transaction. Only the unsigned transaction and
+/// the per-input UTXO fields are interpreted; every other pair is carried
+/// through unchanged in the `unknown` lists.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Psbt {
+    pub unsigned_tx: BitcoinTransaction,
+    pub unknown: Vec<KeyValue>,
+    pub inputs: Vec<PsbtInput>,
+    pub outputs: Vec<PsbtOutput>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct PsbtInput {
+    /// The full transaction that created the spent output.
+    pub non_witness_utxo: Option<BitcoinTransaction>,
+    /// The spent output itself, enough for signing segwit inputs.
+    pub witness_utxo: Option<TransactionOutput>,
+    pub unknown: Vec<KeyValue>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct PsbtOutput {
+    pub unknown: Vec<KeyValue>,
+}
+
+impl Psbt {
+    /// A PSBT for `unsigned_tx` with empty input and output maps. Any
+    /// scriptSigs and witnesses are cleared, as BIP174 requires.
+    pub fn new(mut unsigned_tx: BitcoinTransaction) -> Self {
+        for input in &mut unsigned_tx.inputs {
+            input.script_sig = Script::new(Vec::new());
+            input.witness = Witness::default();
+        }
+        Psbt {
+            inputs: vec![PsbtInput::default(); unsigned_tx.inputs.len()],
+            outputs: vec![PsbtOutput::default(); unsigned_tx.outputs.len()],
+            unsigned_tx,
+            unknown: Vec::new(),
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut b = MAGIC.to_vec();
+        write_pair(
+            &mut b,
+            &[GLOBAL_UNSIGNED_TX],
+            &self.unsigned_tx.to_bytes_without_witness(),
+        );
+        write_unknown_and_end(&mut b, &self.unknown);
+        for input in &self.inputs {
+            if let Some(tx) = &input.non_witness_utxo {
+                write_pair(&mut b, &[INPUT_NON_WITNESS_UTXO], &tx.to_bytes());
+            }
+            if let Some(output) = &input.witness_utxo {
+                write_pair(&mut b, &[INPUT_WITNESS_UTXO], &output.to_bytes());
+            }
+            write_unknown_and_end(&mut b, &input.unknown);
+        }
+        for output in &self.outputs {
+            write_unknown_and_end(&mut b, &output.unknown);
+        }
+        b
+    }
+
+    /// Parses a binary PSBT. A missing magic or unsigned transaction, an
+    /// unsigned transaction with a scriptSig, a duplicate key, or a known
+    /// value with trailing bytes is `InvalidFormat`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, usize), BitcoinError> {
+        let magic = bytes
+            .get(..MAGIC.len())
+            .ok_or(BitcoinError::InsufficientBytes)?;
+        if magic != MAGIC {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        let mut offset = MAGIC.len();
+
+        let (global, len) = read_map(rest(bytes, offset)?)?;
+        offset = advance(offset, len)?;
+        let mut unsigned_tx = None;
+        let mut unknown = Vec::new();
+        for (key, value) in global {
+            if key == [GLOBAL_UNSIGNED_TX] {
+                let parsed = BitcoinTransaction::from_bytes_without_witness(&value)?;
+                unsigned_tx = Some(whole(&value, parsed)?);
+            } else {
+                unknown.push((key, value));
+            }
+        }
+        let unsigned_tx = unsigned_tx.ok_or(BitcoinError::InvalidFormat)?;
+        if unsigned_tx
+            .inputs
+            .iter()
+            .any(|input| !input.script_sig.is_empty())
+        {
+            return Err(BitcoinError::InvalidFormat);
+        }
+
+        let mut inputs = Vec::new();
+        for _ in 0..unsigned_tx.inputs.len() {
+            let (pairs, len) = read_map(rest(bytes, offset)?)?;
+            offset = advance(offset, len)?;
+            let mut input = PsbtInput::default();
+            for (key, value) in pairs {
+                if key == [INPUT_NON_WITNESS_UTXO] {
+                    let parsed = BitcoinTransaction::from_bytes(&value)?;
+                    input.non_witness_utxo = Some(whole(&value, parsed)?);
+                } else if key == [INPUT_WITNESS_UTXO] {
+                    let parsed = TransactionOutput::from_bytes(&value)?;
+                    input.witness_utxo = Some(whole(&value, parsed)?);
+                } else {
+                    input.unknown.push((key, value));
+                }
+            }
+            inputs.push(input);
+        }
+
+        let mut outputs = Vec::new();
+        for _ in 0..unsigned_tx.outputs.len() {
+            let (unknown, len) = read_map(rest(bytes, offset)?)?;
+            offset = advance(offset, len)?;
+            outputs.push(PsbtOutput { unknown });
+        }
+
+        Ok((
+            Psbt {
+                unsigned_tx,
+                unknown,
+                inputs,
+                outputs,
+            },
+            offset,
+        ))
+    }
+}
+
+fn write_pair(b: &mut Vec<u8>, key: &[u8], value: &[u8]) {
+    b.extend_from_slice(&write_var_bytes(key));
+    b.extend_from_slice(&write_var_bytes(value));
+}
+
+fn write_unknown_and_end(b: &mut Vec<u8>, unknown: &[KeyValue]) {
+    for (key, value) in unknown {
+        write_pair(b, key, value);
+    }
+    b.push(0x00);
+}
+
+/// Reads key-value pairs up to and including the `0x00` separator.
+fn read_map(bytes: &[u8]) -> Result<(Vec<KeyValue>, usize), BitcoinError> {
+    let mut pairs: Vec<KeyValue> = Vec::new();
+    let mut offset = 0;
+    loop {
+        match bytes.get(offset) {
+            None => return Err(BitcoinError::InsufficientBytes),
+            Some(0x00) => return Ok((pairs, advance(offset, 1)?)),
+            Some(_) => {}
+        }
+        let (key, key_len) = read_var_bytes(rest(bytes, offset)?)?;
+        offset = advance(offset, key_len)?;
+        let (value, value_len) = read_var_bytes(rest(bytes, offset)?)?;
+        offset = advance(offset, value_len)?;
+        if pairs.iter().any(|(existing, _)| *existing == key) {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        pairs.push((key, value));
+    }
+}
+
+/// Unwraps what a parser returned for `value`, rejecting unread bytes.
+fn whole<T>(value: &[u8], (decoded, len): (T, usize)) -> Result<T, BitcoinError> {
+    if len != value.len() {
+        return Err(BitcoinError::InvalidFormat);
+    }
+    Ok(decoded)
+}
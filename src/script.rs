@@ -0,0 +1,640 @@
+use crate::policy::MAX_STANDARD_SCRIPTSIG_SIZE;
+use crate::{BitcoinError, BitcoinTransaction, Script, TransactionInput};
+use serde::{Deserialize, Serialize};
+
+pub mod opcodes {
+    pub const OP_0: u8 = 0x00;
+    pub const OP_PUSHDATA1: u8 = 0x4c;
+    pub const OP_PUSHDATA2: u8 = 0x4d;
+    pub const OP_PUSHDATA4: u8 = 0x4e;
+    pub const OP_1NEGATE: u8 = 0x4f;
+    pub const OP_1: u8 = 0x51;
+    pub const OP_16: u8 = 0x60;
+    pub const OP_RETURN: u8 = 0x6a;
+    pub const OP_DUP: u8 = 0x76;
+    pub const OP_EQUAL: u8 = 0x87;
+    pub const OP_EQUALVERIFY: u8 = 0x88;
+    pub const OP_HASH160: u8 = 0xa9;
+    pub const OP_CHECKSIG: u8 = 0xac;
+    pub const OP_CHECKSIGVERIFY: u8 = 0xad;
+    pub const OP_CHECKMULTISIG: u8 = 0xae;
+    pub const OP_CHECKMULTISIGVERIFY: u8 = 0xaf;
+    pub const OP_CHECKLOCKTIMEVERIFY: u8 = 0xb1;
+    pub const OP_CHECKSEQUENCEVERIFY: u8 = 0xb2;
+}
+
+use opcodes::*;
+
+const P2A_SCRIPT: [u8; 4] = [OP_1, 0x02, 0x4e, 0x73];
+
+/// Consensus limit on the size of a script that is executed.
+pub const MAX_SCRIPT_SIZE: usize = 10_000;
+
+/// One parsed script element: either a data push (including `OP_0`, which
+/// pushes the empty array) or any other opcode.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Instruction<'a> {
+    PushBytes(&'a [u8]),
+    Op(u8),
+}
+
+impl Instruction<'_> {
+    /// The small integer pushed by `OP_0`, `OP_1NEGATE` or `OP_1`..`OP_16`,
+    /// or the value of a data push of at most four bytes, the limit for
+    /// arithmetic operands.
+    pub fn as_number(&self) -> Option<i64> {
+        match *self {
+            Instruction::PushBytes(data) if data.len() <= 4 => Some(decode_number(data)),
+            _ => self.as_small_number(),
+        }
+    }
+
+    /// Like `as_number`, but for a CLTV or CSV operand: a minimally encoded
+    /// push of up to `MAX_SCRIPT_NUM_LEN` bytes.
+    fn as_lock_time_number(&self) -> Option<i64> {
+        match *self {
+            Instruction::PushBytes(data) => script_num_decode(data).ok(),
+            _ => self.as_small_number(),
+        }
+    }
+
+    fn as_small_number(&self) -> Option<i64> {
+        match *self {
+            Instruction::Op(OP_1NEGATE) => Some(-1),
+            Instruction::Op(op @ OP_1..=OP_16) => Some((op - OP_1 + 1) as i64),
+            _ => None,
+        }
+    }
+}
+
+fn decode_number(data: &[u8]) -> i64 {
+    let Some((&last, _)) = data.split_last() else {
+        return 0;
+    };
+    let mut value = 0i64;
+    for (i, &byte) in data.iter().enumerate() {
+        value |= (byte as i64) << (8 * i);
+    }
+    if last & 0x80 != 0 {
+        -(value & !(0x80i64 << (8 * (data.len() - 1))))
+    } else {
+        value
+    }
+}
+
+/// Longest number operand accepted by `script_num_decode`, as for the
+/// CLTV and CSV arguments.
+pub const MAX_SCRIPT_NUM_LEN: usize = 5;
+
+/// Encodes `n` as a minimal script number: little-endian magnitude with the
+/// sign in the top bit of the last byte. Zero is the empty array.
+pub fn script_num_encode(n: i64) -> Vec<u8> {
+    let mut magnitude = n.unsigned_abs();
+    let mut bytes = Vec::new();
+    while magnitude > 0 {
+        bytes.push(magnitude as u8);
+        magnitude >>= 8;
+    }
+    match bytes.last_mut() {
+        Some(last) if *last & 0x80 != 0 => bytes.push(if n < 0 { 0x80 } else { 0x00 }),
+        Some(last) if n < 0 => *last |= 0x80,
+        _ => {}
+    }
+    bytes
+}
+
+/// Decodes a script number, rejecting with `InvalidFormat` an encoding
+/// longer than `MAX_SCRIPT_NUM_LEN` or one with a needless trailing byte.
+pub fn script_num_decode(bytes: &[u8]) -> Result<i64, BitcoinError> {
+    script_num_decode_with_max(bytes, MAX_SCRIPT_NUM_LEN)
+}
+
+/// Like `script_num_decode`, but accepting at most `max_len` bytes: 4 for the
+/// arithmetic opcodes, 5 for the CLTV and CSV arguments.
+pub fn script_num_decode_with_max(bytes: &[u8], max_len: usize) -> Result<i64, BitcoinError> {
+    if bytes.len() > max_len {
+        return Err(BitcoinError::InvalidFormat);
+    }
+    if let Some((&last, init)) = bytes.split_last() {
+        // The last byte may only be 0x00 or 0x80 when it carries the sign bit
+        // that the byte before it could not.
+        if last & 0x7f == 0 && init.last().is_none_or(|&b| b & 0x80 == 0) {
+            return Err(BitcoinError::InvalidFormat);
+        }
+    }
+    Ok(decode_number(bytes))
+}
+
+/// Iterator over the instructions of a script. A push that runs past the end
+/// of the script yields `InsufficientBytes` and ends the iteration.
+#[derive(Debug, Clone)]
+pub struct Instructions<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Iterator for Instructions<'a> {
+    type Item = Result<Instruction<'a>, BitcoinError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (&op, rest) = self.bytes.split_first()?;
+        let (len, rest) = match op {
+            0x01..=0x4b => (op as usize, rest),
+            OP_PUSHDATA1 | OP_PUSHDATA2 | OP_PUSHDATA4 => {
+                let width = match op {
+                    OP_PUSHDATA1 => 1,
+                    OP_PUSHDATA2 => 2,
+                    _ => 4,
+                };
+                if rest.len() < width {
+                    self.bytes = &[];
+                    return Some(Err(BitcoinError::InsufficientBytes));
+                }
+                let mut len_bytes = [0u8; 4];
+                len_bytes[..width].copy_from_slice(&rest[..width]);
+                (u32::from_le_bytes(len_bytes) as usize, &rest[width..])
+            }
+            OP_0 => {
+                self.bytes = rest;
+                return Some(Ok(Instruction::PushBytes(&[])));
+            }
+            _ => {
+                self.bytes = rest;
+                return Some(Ok(Instruction::Op(op)));
+            }
+        };
+        if rest.len() < len {
+            self.bytes = &[];
+            return Some(Err(BitcoinError::InsufficientBytes));
+        }
+        let (data, rest) = rest.split_at(len);
+        self.bytes = rest;
+        Some(Ok(Instruction::PushBytes(data)))
+    }
+}
+
+/// A timelock enforced by a script, with the value pushed just before the
+/// opcode that checks it.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum TimelockConstraint {
+    /// `OP_CHECKLOCKTIMEVERIFY`: compared against the spending transaction's lock time.
+    Cltv(i64),
+    /// `OP_CHECKSEQUENCEVERIFY`: compared against the spending input's sequence.
+    Csv(i64),
+}
+
+/// The standard output templates.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub enum ScriptType {
+    P2pk,
+    P2pkh,
+    P2sh,
+    P2wpkh,
+    P2wsh,
+    P2tr,
+    /// Pay-to-anchor, `OP_1 <0x4e73>`: a keyless output anyone can spend,
+    /// used for fee bumping by CPFP.
+    P2a,
+    NullData,
+    NonStandard,
+}
+
+impl Script {
+    /// The BIP143 scriptCode for spending a P2WPKH output: the P2PKH script
+    /// for the same key hash. `to_bytes` gives the length-prefixed form
+    /// `0x1976a914{hash}88ac` that goes into the sighash preimage.
+    pub fn p2wpkh_script_code(pubkey_hash: &[u8; 20]) -> Script {
+        let mut bytes = vec![OP_DUP, OP_HASH160, 0x14];
+        bytes.extend_from_slice(pubkey_hash);
+        bytes.extend_from_slice(&[OP_EQUALVERIFY, OP_CHECKSIG]);
+        Script::new(bytes)
+    }
+
+    pub fn instructions(&self) -> Instructions<'_> {
+        Instructions { bytes: &self.bytes }
+    }
+
+    /// Collects the CLTV and CSV checks in the script. An opcode not preceded
+    /// by a minimal number push of up to five bytes is skipped, and parsing
+    /// stops at a malformed push.
+    pub fn timelocks(&self) -> Vec<TimelockConstraint> {
+        let mut timelocks = Vec::new();
+        let mut previous: Option<Instruction<'_>> = None;
+        for instruction in self.instructions() {
+            let Ok(instruction) = instruction else {
+                break;
+            };
+            let value = previous.and_then(|p| p.as_lock_time_number());
+            match (instruction, value) {
+                (Instruction::Op(OP_CHECKLOCKTIMEVERIFY), Some(n)) => {
+                    timelocks.push(TimelockConstraint::Cltv(n))
+                }
+                (Instruction::Op(OP_CHECKSEQUENCEVERIFY), Some(n)) => {
+                    timelocks.push(TimelockConstraint::Csv(n))
+                }
+                _ => {}
+            }
+            previous = Some(instruction);
+        }
+        timelocks
+    }
+
+    /// Whether every element is a data push or a small-number opcode up to
+    /// `OP_16`, as relay policy requires of a scriptSig. A malformed push
+    /// makes the script not push-only.
+    pub fn is_push_only(&self) -> bool {
+        self.instructions().all(|instruction| match instruction {
+            Ok(Instruction::PushBytes(_)) => true,
+            Ok(Instruction::Op(op)) => op <= OP_16,
+            Err(_) => false,
+        })
+    }
+
+    /// Whether every data push uses the shortest encoding for its data, as the
+    /// `MINIMALDATA` rule requires: `OP_0`, `OP_1NEGATE` or `OP_1`..`OP_16`
+    /// where one exists, then a direct push, then the smallest `OP_PUSHDATA`.
+    /// Non-push opcodes are ignored; a malformed push fails.
+    pub fn has_minimal_pushes(&self) -> bool {
+        let mut instructions = self.instructions();
+        loop {
+            let op = instructions.bytes.first().copied();
+            let data = match instructions.next() {
+                None => return true,
+                Some(Err(_)) => return false,
+                Some(Ok(Instruction::Op(_))) => continue,
+                Some(Ok(Instruction::PushBytes(data))) => data,
+            };
+            let minimal = match data {
+                [] => OP_0,
+                [n @ 1..=16] => OP_1 + n - 1,
+                [0x81] => OP_1NEGATE,
+                _ if data.len() <= 0x4b => data.len() as u8,
+                _ if data.len() <= 0xff => OP_PUSHDATA1,
+                _ if data.len() <= 0xffff => OP_PUSHDATA2,
+                _ => OP_PUSHDATA4,
+            };
+            if op != Some(minimal) {
+                return false;
+            }
+        }
+    }
+
+    /// Whether the script fits the `MAX_STANDARD_SCRIPTSIG_SIZE` relay limit
+    /// for a scriptSig.
+    pub fn scriptsig_size_ok(&self) -> bool {
+        self.bytes.len() <= MAX_STANDARD_SCRIPTSIG_SIZE
+    }
+
+    /// Compares the parsed instructions rather than the bytes, so the same
+    /// data pushed with different push opcodes compares equal. Scripts that
+    /// do not parse are only equal when their bytes are.
+    pub fn semantic_eq(&self, other: &Script) -> bool {
+        let ours: Result<Vec<_>, _> = self.instructions().collect();
+        let theirs: Result<Vec<_>, _> = other.instructions().collect();
+        match (ours, theirs) {
+            (Ok(ours), Ok(theirs)) => ours == theirs,
+            _ => self.bytes == other.bytes,
+        }
+    }
+
+    /// Counts the signature operations in the script: one per `OP_CHECKSIG`
+    /// or `OP_CHECKSIGVERIFY`, and for `OP_CHECKMULTISIG(VERIFY)` the key
+    /// count pushed by the preceding `OP_1`..`OP_16`, or 20 without one.
+    /// Counting stops at a malformed push.
+    pub fn sigop_count(&self) -> usize {
+        let mut count = 0;
+        let mut previous = None;
+        for instruction in self.instructions() {
+            let Ok(instruction) = instruction else {
+                break;
+            };
+            match instruction {
+                Instruction::Op(OP_CHECKSIG | OP_CHECKSIGVERIFY) => count += 1,
+                Instruction::Op(OP_CHECKMULTISIG | OP_CHECKMULTISIGVERIFY) => {
+                    count += match previous {
+                        Some(Instruction::Op(op @ OP_1..=OP_16)) => (op - OP_1 + 1) as usize,
+                        _ => 20,
+                    }
+                }
+                _ => {}
+            }
+            previous = Some(instruction);
+        }
+        count
+    }
+
+    /// Classifies the script as one of the standard output templates.
+    pub fn script_type(&self) -> ScriptType {
+        let b = &self.bytes;
+        match b.len() {
+            25 if b[0] == OP_DUP
+                && b[1] == OP_HASH160
+                && b[2] == 0x14
+                && b[23] == OP_EQUALVERIFY
+                && b[24] == OP_CHECKSIG =>
+            {
+                ScriptType::P2pkh
+            }
+            23 if b[0] == OP_HASH160 && b[1] == 0x14 && b[22] == OP_EQUAL => ScriptType::P2sh,
+            22 if b[0] == OP_0 && b[1] == 0x14 => ScriptType::P2wpkh,
+            34 if b[0] == OP_0 && b[1] == 0x20 => ScriptType::P2wsh,
+            34 if b[0] == OP_1 && b[1] == 0x20 => ScriptType::P2tr,
+            4 if b[..] == P2A_SCRIPT => ScriptType::P2a,
+            35 if b[0] == 0x21 && b[34] == OP_CHECKSIG => ScriptType::P2pk,
+            67 if b[0] == 0x41 && b[66] == OP_CHECKSIG => ScriptType::P2pk,
+            _ if b.first() == Some(&OP_RETURN) => ScriptType::NullData,
+            _ => ScriptType::NonStandard,
+        }
+    }
+
+    /// The data carried by an `OP_RETURN` script: the first push after the
+    /// `OP_RETURN`, ignoring anything after it. A bare `OP_RETURN` carries an
+    /// empty payload. Returns `None` for any other script, or when the
+    /// `OP_RETURN` is followed by a non-push opcode or a truncated push.
+    pub fn op_return_data(&self) -> Option<&[u8]> {
+        let mut instructions = self.instructions();
+        if instructions.next() != Some(Ok(Instruction::Op(OP_RETURN))) {
+            return None;
+        }
+        match instructions.next() {
+            None => Some(&[]),
+            Some(Ok(Instruction::PushBytes(data))) => Some(data),
+            _ => None,
+        }
+    }
+
+    /// Every data push after the `OP_RETURN`, for protocols that split their
+    /// payload across several pushes. `None` under the same conditions as
+    /// `op_return_data`, or when any later element is not a push.
+    pub fn op_return_pushes(&self) -> Option<Vec<&[u8]>> {
+        let mut instructions = self.instructions();
+        if instructions.next() != Some(Ok(Instruction::Op(OP_RETURN))) {
+            return None;
+        }
+        instructions
+            .map(|instruction| match instruction {
+                Ok(Instruction::PushBytes(data)) => Some(data),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Whether an output with this script can never be spent: it starts with
+    /// `OP_RETURN` or is larger than `MAX_SCRIPT_SIZE`. Such outputs need not
+    /// be kept in the UTXO set.
+    pub fn is_provably_unspendable(&self) -> bool {
+        self.bytes.first() == Some(&OP_RETURN) || self.bytes.len() > MAX_SCRIPT_SIZE
+    }
+
+    /// Whether this is the pay-to-anchor script that ephemeral anchor outputs
+    /// use.
+    pub fn is_ephemeral_anchor(&self) -> bool {
+        self.script_type() == ScriptType::P2a
+    }
+
+    pub fn starts_with(&self, prefix: &[u8]) -> bool {
+        self.bytes.starts_with(prefix)
+    }
+
+    /// The segwit version of a witness program script (`OP_0` or `OP_1`..`OP_16`
+    /// followed by a single 2 to 40 byte push), or `None` for any other script.
+    pub fn witness_version(&self) -> Option<u8> {
+        self.witness_program().map(|(version, _)| version)
+    }
+
+    /// The version and program of a witness output: `OP_0` or
+    /// `OP_1`..`OP_16` followed by one push of 2 to 40 bytes.
+    pub fn witness_program(&self) -> Option<(u8, Vec<u8>)> {
+        let b = &self.bytes;
+        if !(4..=42).contains(&b.len()) || b[1] as usize + 2 != b.len() {
+            return None;
+        }
+        let version = match b[0] {
+            OP_0 => 0,
+            op @ OP_1..=OP_16 => op - OP_1 + 1,
+            _ => return None,
+        };
+        Some((version, b[2..].to_vec()))
+    }
+
+    /// Returns the 20-byte key hash of a P2PKH or P2WPKH script.
+    pub fn pubkey_hash(&self) -> Option<[u8; 20]> {
+        let range = match self.script_type() {
+            ScriptType::P2pkh => 3..23,
+            ScriptType::P2wpkh => 2..22,
+            _ => return None,
+        };
+        self.bytes[range].try_into().ok()
+    }
+
+    /// Matches a bare `OP_m <pubkeys...> OP_n OP_CHECKMULTISIG` script and
+    /// returns `m`, the keys and `n`. The keys must be 33 or 65 bytes, there
+    /// must be exactly `n` of them, and `m` may not exceed `n`.
+    pub fn parse_bare_multisig(&self) -> Option<(u8, Vec<Vec<u8>>, u8)> {
+        let small_int = |instruction| match instruction {
+            Some(Ok(Instruction::Op(op @ OP_1..=OP_16))) => Some(op - OP_1 + 1),
+            _ => None,
+        };
+        let mut instructions = self.instructions().peekable();
+        let m = small_int(instructions.next())?;
+        let mut pubkeys = Vec::new();
+        while let Some(Ok(Instruction::PushBytes(key))) = instructions.peek() {
+            if !matches!(key.len(), 33 | 65) {
+                return None;
+            }
+            pubkeys.push(key.to_vec());
+            instructions.next();
+        }
+        let n = small_int(instructions.next())?;
+        if instructions.next() != Some(Ok(Instruction::Op(OP_CHECKMULTISIG)))
+            || instructions.next().is_some()
+            || m > n
+            || pubkeys.len() != n as usize
+        {
+            return None;
+        }
+        Some((m, pubkeys, n))
+    }
+
+    /// Splits a standard P2PKH scriptSig into its signature and public key.
+    /// The script must be exactly two data pushes, with a non-empty signature
+    /// and a 33-byte compressed or 65-byte uncompressed key.
+    pub fn parse_p2pkh_spend(&self) -> Option<(Vec<u8>, Vec<u8>)> {
+        let mut instructions = self.instructions();
+        let (
+            Some(Ok(Instruction::PushBytes(signature))),
+            Some(Ok(Instruction::PushBytes(pubkey))),
+            None,
+        ) = (
+            instructions.next(),
+            instructions.next(),
+            instructions.next(),
+        )
+        else {
+            return None;
+        };
+        if signature.is_empty() || !matches!(pubkey.len(), 33 | 65) {
+            return None;
+        }
+        Some((signature.to_vec(), pubkey.to_vec()))
+    }
+
+    /// The type of the redeem script when this scriptSig is a single push of
+    /// a witness program, as in a wrapped segwit spend.
+    fn wrapped_redeem_script(&self) -> Option<ScriptType> {
+        let mut instructions = self.instructions();
+        let Some(Ok(Instruction::PushBytes(redeem_script))) = instructions.next() else {
+            return None;
+        };
+        if instructions.next().is_some() {
+            return None;
+        }
+        match Script::new(redeem_script.to_vec()).script_type() {
+            script_type @ (ScriptType::P2wpkh | ScriptType::P2wsh) => Some(script_type),
+            _ => None,
+        }
+    }
+}
+
+impl TransactionInput {
+    /// Guesses the type of the spent output from the shape of the scriptSig
+    /// and witness. Returns `None` when the shape is not recognised.
+    fn spent_script_type(&self) -> Option<ScriptType> {
+        let witness = &self.witness.items;
+        if !self.script_sig.is_empty() {
+            return if !witness.is_empty() {
+                Some(ScriptType::P2sh)
+            } else if self.script_sig.parse_p2pkh_spend().is_some() {
+                Some(ScriptType::P2pkh)
+            } else {
+                None
+            };
+        }
+        match witness.len() {
+            1 if matches!(witness[0].len(), 64 | 65) => Some(ScriptType::P2tr),
+            2 if witness[1].len() == 33 => Some(ScriptType::P2wpkh),
+            n if n >= 2 => Some(ScriptType::P2wsh),
+            _ => None,
+        }
+    }
+}
+
+impl BitcoinTransaction {
+    /// Key hashes paid to by P2PKH and P2WPKH outputs, in output order. Other
+    /// output types are skipped.
+    pub fn output_pubkey_hashes(&self) -> Vec<[u8; 20]> {
+        self.outputs
+            .iter()
+            .filter_map(|output| output.script_pubkey.pubkey_hash())
+            .collect()
+    }
+
+    /// A rough signature operation count over the output scripts, as fed to
+    /// `sigop_adjusted_vsize`. Spent scripts and witnesses are not counted.
+    pub fn count_sigops(&self) -> usize {
+        self.outputs
+            .iter()
+            .map(|output| output.script_pubkey.sigop_count())
+            .sum()
+    }
+
+    /// Guesses which output pays change back to the sender, for privacy
+    /// analysis. This is a heuristic, not an authoritative answer: each
+    /// output scores a point for a value that is not a round number of
+    /// 10,000 sats and a point for matching the script type every input
+    /// appears to spend. Returns the single highest scorer, or `None` when
+    /// there are fewer than two outputs, any two outputs have equal values,
+    /// or the top score is tied or zero.
+    pub fn likely_change_index(&self) -> Option<usize> {
+        if self.outputs.len() < 2 {
+            return None;
+        }
+        let mut values: Vec<u64> = self.outputs.iter().map(|output| output.value).collect();
+        values.sort_unstable();
+        values.dedup();
+        if values.len() != self.outputs.len() {
+            return None;
+        }
+
+        let mut input_types = self.inputs.iter().map(TransactionInput::spent_script_type);
+        let first = input_types.next().flatten();
+        let input_type = first.filter(|_| input_types.all(|t| t == first));
+
+        let scores: Vec<u32> = self
+            .outputs
+            .iter()
+            .map(|output| {
+                let unround = u32::from(output.value % 10_000 != 0);
+                let same_type = u32::from(Some(output.script_pubkey.script_type()) == input_type);
+                unround + same_type
+            })
+            .collect();
+        let best = *scores.iter().max()?;
+        let mut top = (0..scores.len()).filter(|&i| scores[i] == best);
+        match (top.next(), top.next()) {
+            (Some(index), None) if best > 0 => Some(index),
+            _ => None,
+        }
+    }
+
+    /// Every non-empty data push in the scriptSigs and every non-empty witness
+    /// item, input by input, with each input's scriptSig pushes before its
+    /// witness items. Parsing of a scriptSig stops at a malformed push.
+    pub fn all_script_data(&self) -> Vec<&[u8]> {
+        let mut data = Vec::new();
+        for input in &self.inputs {
+            for instruction in input.script_sig.instructions() {
+                match instruction {
+                    Ok(Instruction::PushBytes(push)) if !push.is_empty() => data.push(push),
+                    Ok(_) => {}
+                    Err(_) => break,
+                }
+            }
+            data.extend(
+                input
+                    .witness
+                    .items
+                    .iter()
+                    .filter(|item| !item.is_empty())
+                    .map(Vec::as_slice),
+            );
+        }
+        data
+    }
+
+    /// Checks the scriptSig and witness of each input against the type of the
+    /// output it spends. Native segwit spends must have an empty scriptSig,
+    /// with a two-item witness for P2WPKH and a non-empty one for P2WSH and
+    /// P2TR. A P2SH spend whose scriptSig pushes only a P2WPKH or P2WSH
+    /// redeem script needs the same witness as the native form; any other
+    /// P2SH spend must have no witness. Other types are not checked. Fails
+    /// with `InvalidFormat` on the first bad input, or when the slice length
+    /// differs from the input count.
+    pub fn validate_segwit_input_shapes(
+        &self,
+        prevout_script_types: &[ScriptType],
+    ) -> Result<(), BitcoinError> {
+        if prevout_script_types.len() != self.inputs.len() {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        for (input, script_type) in self.inputs.iter().zip(prevout_script_types) {
+            let witness_items = input.witness.len();
+            let valid = match script_type {
+                ScriptType::P2wpkh => input.script_sig.is_empty() && witness_items == 2,
+                ScriptType::P2wsh | ScriptType::P2tr => {
+                    input.script_sig.is_empty() && witness_items > 0
+                }
+                ScriptType::P2a => input.script_sig.is_empty(),
+                ScriptType::P2sh => match input.script_sig.wrapped_redeem_script() {
+                    Some(ScriptType::P2wpkh) => witness_items == 2,
+                    Some(ScriptType::P2wsh) => witness_items > 0,
+                    _ => witness_items == 0,
+                },
+                _ => true,
+            };
+            if !valid {
+                return Err(BitcoinError::InvalidFormat);
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,211 @@
+use crate::hashes::double_sha256;
+use crate::{BitcoinError, BitcoinTransaction, CompactSize, Script};
+
+/// Sign every input and output.
+pub const SIGHASH_ALL: u32 = 0x01;
+/// Sign the inputs but none of the outputs.
+pub const SIGHASH_NONE: u32 = 0x02;
+/// Sign the inputs and only the output at the signed input's index.
+pub const SIGHASH_SINGLE: u32 = 0x03;
+/// Combined with one of the above: commit to the signed input only, so
+/// others can add inputs after signing.
+pub const SIGHASH_ANYONECANPAY: u32 = 0x80;
+
+/// The bits of a sighash type that select ALL, NONE or SINGLE.
+pub(crate) const SIGHASH_BASE_MASK: u32 = 0x1f;
+
+/// The transaction-wide hashes from BIP143. They are the same for every input
+/// signed with `SIGHASH_ALL`, so computing them once saves rehashing the whole
+/// transaction for each input.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Bip143Cache {
+    pub hash_prevouts: [u8; 32],
+    pub hash_sequence: [u8; 32],
+    pub hash_outputs: [u8; 32],
+}
+
+impl BitcoinTransaction {
+    pub fn bip143_cache(&self) -> Bip143Cache {
+        let mut prevouts = Vec::new();
+        let mut sequences = Vec::new();
+        for input in &self.inputs {
+            prevouts.extend_from_slice(&input.previous_output.to_bytes());
+            sequences.extend_from_slice(&input.sequence.to_le_bytes());
+        }
+        let mut outputs = Vec::new();
+        for output in &self.outputs {
+            outputs.extend_from_slice(&output.to_bytes());
+        }
+        Bip143Cache {
+            hash_prevouts: double_sha256(&prevouts),
+            hash_sequence: double_sha256(&sequences),
+            hash_outputs: double_sha256(&outputs),
+        }
+    }
+
+    /// Computes the BIP143 signature hash for a segwit v0 input spending
+    /// `value` satoshis locked by `script_code`. Pass a cache from
+    /// `bip143_cache` when signing several inputs of the same transaction;
+    /// `None` computes the shared hashes on the fly.
+    pub fn segwit_sighash(
+        &self,
+        input_index: usize,
+        script_code: &Script,
+        value: u64,
+        sighash_type: u32,
+        cache: Option<&Bip143Cache>,
+    ) -> Result<[u8; 32], BitcoinError> {
+        let input = self
+            .inputs
+            .get(input_index)
+            .ok_or(BitcoinError::InvalidFormat)?;
+        let computed;
+        let cache = match cache {
+            Some(cache) => cache,
+            None => {
+                computed = self.bip143_cache();
+                &computed
+            }
+        };
+
+        let anyone_can_pay = sighash_type & SIGHASH_ANYONECANPAY != 0;
+        let base_type = sighash_type & SIGHASH_BASE_MASK;
+        let zero = [0u8; 32];
+
+        let mut preimage = self.version.to_le_bytes().to_vec();
+        if anyone_can_pay {
+            preimage.extend_from_slice(&zero);
+        } else {
+            preimage.extend_from_slice(&cache.hash_prevouts);
+        }
+        if anyone_can_pay || base_type == SIGHASH_NONE || base_type == SIGHASH_SINGLE {
+            preimage.extend_from_slice(&zero);
+        } else {
+            preimage.extend_from_slice(&cache.hash_sequence);
+        }
+        preimage.extend_from_slice(&input.previous_output.to_bytes());
+        preimage.extend_from_slice(&script_code.to_bytes());
+        preimage.extend_from_slice(&value.to_le_bytes());
+        preimage.extend_from_slice(&input.sequence.to_le_bytes());
+        if base_type != SIGHASH_NONE && base_type != SIGHASH_SINGLE {
+            preimage.extend_from_slice(&cache.hash_outputs);
+        } else if base_type == SIGHASH_SINGLE && input_index < self.outputs.len() {
+            preimage.extend_from_slice(&double_sha256(&self.outputs[input_index].to_bytes()));
+        } else {
+            preimage.extend_from_slice(&zero);
+        }
+        preimage.extend_from_slice(&self.lock_time.to_le_bytes());
+        preimage.extend_from_slice(&sighash_type.to_le_bytes());
+        Ok(double_sha256(&preimage))
+    }
+}
+
+impl BitcoinTransaction {
+    /// Computes the pre-segwit signature hash for input `input_index`, with
+    /// `script_code` (normally the previous output's scriptPubKey) standing in
+    /// for that input's scriptSig. `OP_CODESEPARATOR` is not handled.
+    pub fn legacy_sighash(
+        &self,
+        input_index: usize,
+        script_code: &Script,
+        sighash_type: u32,
+    ) -> Result<[u8; 32], BitcoinError> {
+        if input_index >= self.inputs.len() {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        Ok(self.legacy_sighash_with_outputs(
+            input_index,
+            script_code,
+            sighash_type,
+            &self.serialized_outputs(),
+        ))
+    }
+
+    /// Computes `legacy_sighash` for every input, with `prevout_scripts[i]` as
+    /// the script code for input `i`. The outputs are serialized once and
+    /// shared between inputs.
+    pub fn all_legacy_sighashes(
+        &self,
+        prevout_scripts: &[Script],
+        sighash_type: u32,
+    ) -> Result<Vec<[u8; 32]>, BitcoinError> {
+        if prevout_scripts.len() != self.inputs.len() {
+            return Err(BitcoinError::InvalidFormat);
+        }
+        let outputs = self.serialized_outputs();
+        Ok(prevout_scripts
+            .iter()
+            .enumerate()
+            .map(|(i, script)| self.legacy_sighash_with_outputs(i, script, sighash_type, &outputs))
+            .collect())
+    }
+
+    fn serialized_outputs(&self) -> Vec<u8> {
+        let mut b = CompactSize::new(self.outputs.len() as u64).to_bytes();
+        for output in &self.outputs {
+            b.extend_from_slice(&output.to_bytes());
+        }
+        b
+    }
+
+    fn legacy_sighash_with_outputs(
+        &self,
+        input_index: usize,
+        script_code: &Script,
+        sighash_type: u32,
+        all_outputs: &[u8],
+    ) -> [u8; 32] {
+        let anyone_can_pay = sighash_type & SIGHASH_ANYONECANPAY != 0;
+        let base_type = sighash_type & SIGHASH_BASE_MASK;
+        let single = base_type == SIGHASH_SINGLE;
+        let none = base_type == SIGHASH_NONE;
+
+        // SIGHASH_SINGLE without a matching output signs the value 1, a
+        // long-standing consensus quirk.
+        if single && input_index >= self.outputs.len() {
+            let mut one = [0u8; 32];
+            one[0] = 1;
+            return one;
+        }
+
+        let mut preimage = self.version.to_le_bytes().to_vec();
+        let signed_inputs: Vec<usize> = if anyone_can_pay {
+            vec![input_index]
+        } else {
+            (0..self.inputs.len()).collect()
+        };
+        preimage.extend_from_slice(&CompactSize::new(signed_inputs.len() as u64).to_bytes());
+        for i in signed_inputs {
+            let input = &self.inputs[i];
+            preimage.extend_from_slice(&input.previous_output.to_bytes());
+            if i == input_index {
+                preimage.extend_from_slice(&script_code.to_bytes());
+            } else {
+                preimage.push(0x00);
+            }
+            let sequence = if i != input_index && (single || none) {
+                0
+            } else {
+                input.sequence
+            };
+            preimage.extend_from_slice(&sequence.to_le_bytes());
+        }
+
+        if none {
+            preimage.push(0x00);
+        } else if single {
+            preimage.extend_from_slice(&CompactSize::new(input_index as u64 + 1).to_bytes());
+            for _ in 0..input_index {
+                preimage.extend_from_slice(&u64::MAX.to_le_bytes());
+                preimage.push(0x00);
+            }
+            preimage.extend_from_slice(&self.outputs[input_index].to_bytes());
+        } else {
+            preimage.extend_from_slice(all_outputs);
+        }
+
+        preimage.extend_from_slice(&self.lock_time.to_le_bytes());
+        preimage.extend_from_slice(&sighash_type.to_le_bytes());
+        double_sha256(&preimage)
+    }
+}
@@ -110,4 +110,2833 @@ mod tests {
         assert!(output.contains("Lock Time: 0"));
         assert!(output.contains("Previous Output Vout: 7"));
     }
+
+    #[test]
+    fn test_input_is_final() {
+        let outpoint = OutPoint::new(dummy_txid(1), 0);
+        let final_input = TransactionInput::new(outpoint.clone(), Script::new(vec![]), 0xFFFFFFFF);
+        let non_final = TransactionInput::new(outpoint, Script::new(vec![]), 0xFFFFFFFE);
+        assert!(final_input.is_final());
+        assert!(!non_final.is_final());
+
+        let tx = BitcoinTransaction::new(1, vec![final_input.clone()], 500_000);
+        assert!(tx.is_final());
+        let tx = BitcoinTransaction::new(1, vec![final_input, non_final], 500_000);
+        assert!(!tx.is_final());
+    }
+
+    #[test]
+    fn test_bitcoin_tx_full_roundtrip_with_outputs_and_witness() {
+        let mut input = TransactionInput::new(
+            OutPoint::new(dummy_txid(2), 1),
+            Script::new(vec![]),
+            0xFFFFFFFD,
+        );
+        input.witness = Witness::new(vec![vec![0x30; 71], vec![0x02; 33]]);
+        let mut tx = BitcoinTransaction::new(2, vec![input], 0);
+        tx.outputs.push(TransactionOutput::new(
+            50_000,
+            Script::new(vec![0x00, 0x14, 0xAA, 0xBB]),
+        ));
+
+        let bytes = tx.to_bytes();
+        assert_eq!(&bytes[4..6], &[0x00, 0x01]);
+        let (parsed, consumed) = BitcoinTransaction::from_bytes(&bytes).unwrap();
+        assert_eq!(parsed, tx);
+        assert_eq!(consumed, bytes.len());
+
+        let stripped = tx.to_bytes_without_witness();
+        let (parsed, _) = BitcoinTransaction::from_bytes(&stripped).unwrap();
+        assert!(parsed.inputs[0].witness.is_empty());
+        assert_eq!(parsed.outputs, tx.outputs);
+    }
+
+    #[test]
+    fn test_bitcoin_tx_inputs_only_roundtrip() {
+        let input = TransactionInput::new(
+            OutPoint::new(dummy_txid(3), 0),
+            Script::new(vec![0x51]),
+            0xFFFFFFFF,
+        );
+        let mut tx = BitcoinTransaction::new(1, vec![input], 77);
+        let bytes = tx.to_bytes_inputs_only();
+        let (parsed, consumed) = BitcoinTransaction::from_bytes_inputs_only(&bytes).unwrap();
+        assert_eq!(parsed, tx);
+        assert_eq!(consumed, bytes.len());
+
+        tx.outputs
+            .push(TransactionOutput::new(1_000, Script::new(vec![0x6A])));
+        assert_eq!(tx.to_bytes_inputs_only(), bytes);
+        assert_ne!(tx.to_bytes(), bytes);
+        let (parsed, _) = BitcoinTransaction::from_bytes_inputs_only(&bytes).unwrap();
+        assert!(parsed.outputs.is_empty());
+    }
+
+    #[test]
+    fn test_double_sha256_vectors() {
+        assert_eq!(
+            hex::encode(hashes::double_sha256(b"")),
+            "5df6e0e2761359d30a8275058e299fcc0381534545f55cf43e41983f5d4c9456"
+        );
+        assert_eq!(
+            hex::encode(hashes::double_sha256(b"hello")),
+            "9595c9df90075148eb06860365df33584b75bff782a510c6cd4883a419833d50"
+        );
+        assert_eq!(
+            hex::encode(hashes::sha256(&[0x61; 1000])),
+            "41edece42d63e8d9bf515a9ba6932e1c20cbc9f5a5d134645adb5db1b9737ea3"
+        );
+    }
+
+    #[test]
+    fn test_hash160_vectors() {
+        assert_eq!(
+            hex::encode(hashes::hash160(b"")),
+            "b472a266d0bd89c13706a4132ccfb16f7c3b9fcb"
+        );
+        assert_eq!(
+            hex::encode(hashes::hash160(b"hello")),
+            "b6a9c8c230722b7c748331a8b450f05566dc7d0f"
+        );
+    }
+
+    #[test]
+    fn test_var_bytes_roundtrip() {
+        let data = vec![0xAB; 300];
+        let bytes = write_var_bytes(&data);
+        assert_eq!(&bytes[..3], &[0xFD, 0x2C, 0x01]);
+        let (parsed, consumed) = read_var_bytes(&bytes).unwrap();
+        assert_eq!(parsed, data);
+        assert_eq!(consumed, bytes.len());
+
+        assert_eq!(write_var_bytes(&[]), vec![0x00]);
+        assert_eq!(read_var_bytes(&[0x00, 0xFF]).unwrap(), (vec![], 1));
+    }
+
+    #[test]
+    fn test_var_bytes_rejects_overlong_length() {
+        assert_eq!(
+            read_var_bytes(&[0x05, 0x01, 0x02]),
+            Err(BitcoinError::InsufficientBytes)
+        );
+        let huge = [0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x00];
+        assert_eq!(read_var_bytes(&huge), Err(BitcoinError::InsufficientBytes));
+        assert_eq!(
+            Script::from_bytes(&huge),
+            Err(BitcoinError::InsufficientBytes)
+        );
+    }
+
+    #[test]
+    fn test_estimated_vsize_p2wpkh() {
+        let input = TransactionInput::new(OutPoint::new(dummy_txid(1), 0), Script::new(vec![]), 0);
+        let mut tx = BitcoinTransaction::new(2, vec![input], 0);
+        let mut p2wpkh = vec![0x00, 0x14];
+        p2wpkh.extend_from_slice(&[0x11; 20]);
+        tx.outputs
+            .push(TransactionOutput::new(10_000, Script::new(p2wpkh)));
+
+        // 82 base bytes * 4 + 2 (marker/flag) + 108 witness = 438 wu.
+        assert_eq!(tx.estimated_vsize(&[InputKind::P2wpkh]), 110);
+
+        tx.inputs[0].witness = Witness::new(vec![vec![0x30; 72], vec![0x02; 33]]);
+        assert_eq!(tx.weight(), 438);
+        assert_eq!(tx.vsize(), 110);
+    }
+
+    #[test]
+    fn test_estimated_vsize_p2pkh() {
+        let input = TransactionInput::new(OutPoint::new(dummy_txid(1), 0), Script::new(vec![]), 0);
+        let mut tx = BitcoinTransaction::new(1, vec![input], 0);
+        let mut p2pkh = vec![0x76, 0xA9, 0x14];
+        p2pkh.extend_from_slice(&[0x11; 20]);
+        p2pkh.extend_from_slice(&[0x88, 0xAC]);
+        tx.outputs
+            .push(TransactionOutput::new(10_000, Script::new(p2pkh)));
+
+        assert_eq!(tx.estimated_vsize(&[InputKind::P2pkh]), 192);
+        assert_eq!(tx.estimated_vsize(&[]), 85);
+        assert_eq!(tx.vsize(), 85);
+    }
+
+    #[test]
+    fn test_witness_accessors() {
+        let inputs = vec![
+            TransactionInput::new(OutPoint::new(dummy_txid(1), 0), Script::new(vec![]), 0),
+            TransactionInput::new(OutPoint::new(dummy_txid(2), 0), Script::new(vec![]), 0),
+        ];
+        let mut tx = BitcoinTransaction::new(2, inputs, 0);
+        assert!(tx.witness(2).is_none());
+        assert!(tx.witness_mut(2).is_none());
+
+        let witness = tx.witness_mut(1).unwrap();
+        witness.push(vec![0x30, 0x44]);
+        witness.push(vec![0x02; 33]);
+
+        assert!(tx.witness(0).unwrap().is_empty());
+        let witness = tx.witness(1).unwrap();
+        assert_eq!(witness.len(), 2);
+        assert_eq!(witness.get(0), Some(&[0x30, 0x44][..]));
+        assert_eq!(witness.get(1), Some(&[0x02; 33][..]));
+        assert_eq!(witness.get(2), None);
+        assert!(tx.has_witness());
+    }
+
+    fn der_signature(r: &[u8], s: &[u8]) -> Vec<u8> {
+        let mut der = vec![0x30, (4 + r.len() + s.len()) as u8, 0x02, r.len() as u8];
+        der.extend_from_slice(r);
+        der.push(0x02);
+        der.push(s.len() as u8);
+        der.extend_from_slice(s);
+        der
+    }
+
+    #[test]
+    fn test_low_s_signature() {
+        let r = [0x11; 32];
+        let half_order =
+            hex::decode("7fffffffffffffffffffffffffffffff5d576e7357a4501ddfe92f46681b20a0")
+                .unwrap();
+        let mut sig = der_signature(&r, &half_order);
+        assert!(policy::is_low_s_signature(&sig));
+        sig.push(0x01);
+        assert!(policy::is_low_s_signature(&sig));
+        assert!(policy::is_low_s_signature(&der_signature(&r, &[0x01])));
+    }
+
+    #[test]
+    fn test_high_s_signature() {
+        let r = [0x11; 32];
+        let above_half =
+            hex::decode("7fffffffffffffffffffffffffffffff5d576e7357a4501ddfe92f46681b20a1")
+                .unwrap();
+        assert!(!policy::is_low_s_signature(&der_signature(&r, &above_half)));
+        let mut high = vec![0x00];
+        high.extend_from_slice(&[0xFF; 32]);
+        assert!(!policy::is_low_s_signature(&der_signature(&r, &high)));
+        assert!(!policy::is_low_s_signature(&[0x30, 0x02, 0x02, 0x00]));
+    }
+
+    #[test]
+    fn test_encode_into_appends() {
+        let legacy = BitcoinTransaction::new(
+            1,
+            vec![TransactionInput::new(
+                OutPoint::new(dummy_txid(1), 0),
+                Script::new(vec![0x51]),
+                0xFFFFFFFF,
+            )],
+            0,
+        );
+        let mut segwit = legacy.clone();
+        segwit.inputs[0].witness.push(vec![0x01]);
+        segwit
+            .outputs
+            .push(TransactionOutput::new(1, Script::new(vec![0x6A])));
+
+        let mut buf = vec![0xEE];
+        let first = legacy.encode_into(&mut buf);
+        let second = segwit.encode_into(&mut buf);
+
+        let mut expected = vec![0xEE];
+        expected.extend_from_slice(&legacy.to_bytes());
+        expected.extend_from_slice(&segwit.to_bytes());
+        assert_eq!(buf, expected);
+        assert_eq!(first, legacy.to_bytes().len());
+        assert_eq!(second, segwit.to_bytes().len());
+    }
+
+    fn dummy_header(nonce: u32) -> block::BlockHeader {
+        block::BlockHeader::new(
+            0x2000_0000,
+            dummy_txid(1),
+            dummy_txid(2),
+            1_700_000_000,
+            0x1703_4219,
+            nonce,
+        )
+    }
+
+    #[test]
+    fn test_headers_message_roundtrip() {
+        let first = dummy_header(1);
+        let second = block::BlockHeader::new(
+            0x2000_0000,
+            first.block_hash(),
+            dummy_txid(3),
+            1_700_000_600,
+            0x1703_4219,
+            2,
+        );
+        let msg = p2p::HeadersMessage::new(vec![first, second]);
+        let bytes = msg.to_bytes();
+        assert_eq!(bytes.len(), 1 + 2 * 81);
+        assert_eq!(bytes[81], 0x00);
+        let (parsed, consumed) = p2p::HeadersMessage::from_bytes(&bytes).unwrap();
+        assert_eq!(parsed, msg);
+        assert_eq!(consumed, bytes.len());
+
+        let mut bad = bytes.clone();
+        bad[81] = 0x01;
+        assert_eq!(
+            p2p::HeadersMessage::from_bytes(&bad),
+            Err(BitcoinError::InvalidFormat)
+        );
+        assert_eq!(
+            p2p::HeadersMessage::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(BitcoinError::InsufficientBytes)
+        );
+    }
+
+    #[test]
+    fn test_genesis_block_header_hash() {
+        let merkle_root: [u8; 32] =
+            hex::decode("3ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a")
+                .unwrap()
+                .try_into()
+                .unwrap();
+        let header = block::BlockHeader::new(
+            1,
+            [0u8; 32],
+            merkle_root,
+            1231006505,
+            0x1d00ffff,
+            2083236893,
+        );
+        let mut hash = header.block_hash();
+        hash.reverse();
+        assert_eq!(
+            hex::encode(hash),
+            "000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f"
+        );
+        let (parsed, consumed) = block::BlockHeader::from_bytes(&header.to_bytes()).unwrap();
+        assert_eq!(parsed, header);
+        assert_eq!(consumed, 80);
+    }
+
+    #[test]
+    fn test_txid_byte_order() {
+        let hex_str = "0102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f20";
+        let raw: [u8; 32] = hex::decode(hex_str).unwrap().try_into().unwrap();
+        let internal = Txid::from_raw_bytes(raw);
+        let display = Txid::from_display_hex(hex_str).unwrap();
+
+        let mut reversed = raw;
+        reversed.reverse();
+        assert_eq!(display.to_raw_bytes(), reversed);
+        assert_eq!(internal.to_raw_bytes(), raw);
+        assert_eq!(display.to_display_string(), hex_str);
+        assert_eq!(hex::encode(internal.to_raw_bytes()), hex_str);
+
+        assert_eq!(
+            Txid::from_display_hex("zz"),
+            Err(BitcoinError::InvalidFormat)
+        );
+        assert_eq!(
+            Txid::from_display_hex("0102"),
+            Err(BitcoinError::InvalidFormat)
+        );
+    }
+
+    fn p2wpkh_script(byte: u8) -> Script {
+        let mut bytes = vec![0x00, 0x14];
+        bytes.extend_from_slice(&[byte; 20]);
+        Script::new(bytes)
+    }
+
+    #[test]
+    fn test_build_checked_rejects_dust() {
+        let builder = TransactionBuilder::new()
+            .add_input(OutPoint::new(dummy_txid(1), 0))
+            .add_output(Amount::from_sat(50_000), p2wpkh_script(0x11))
+            .add_output(Amount::from_sat(1), p2wpkh_script(0x22));
+
+        let err = builder
+            .build_checked(policy::DEFAULT_DUST_RELAY_FEE)
+            .unwrap_err();
+        assert_eq!(
+            err,
+            vec![policy::StandardnessViolation::Dust { output_index: 1 }]
+        );
+
+        let tx = builder.build();
+        assert_eq!(tx.version, 2);
+        assert_eq!(tx.outputs.len(), 2);
+        assert_eq!(tx.outputs[1].value, 1);
+    }
+
+    #[test]
+    fn test_build_checked_accepts_standard_tx() {
+        let tx = TransactionBuilder::new()
+            .add_input(OutPoint::new(dummy_txid(1), 0))
+            .add_output(Amount::from_sat(294), p2wpkh_script(0x11))
+            .build_checked(policy::DEFAULT_DUST_RELAY_FEE)
+            .unwrap();
+        assert_eq!(
+            tx.outputs[0].dust_threshold(policy::DEFAULT_DUST_RELAY_FEE),
+            Amount::from_sat(294)
+        );
+        // 98 vbytes at 1001 sat/kvB is 98.098 satoshis, rounded up.
+        assert_eq!(
+            tx.outputs[0].dust_threshold(FeeRate::from_sat_per_kvb(1_001)),
+            Amount::from_sat(99)
+        );
+
+        let err = TransactionBuilder::new()
+            .build_checked(policy::DEFAULT_DUST_RELAY_FEE)
+            .unwrap_err();
+        assert_eq!(
+            err,
+            vec![
+                policy::StandardnessViolation::NoInputs,
+                policy::StandardnessViolation::NoOutputs
+            ]
+        );
+    }
+
+    fn block_file_bytes() -> (Vec<u8>, Vec<BitcoinTransaction>) {
+        let mut txs = Vec::new();
+        for i in 0..2u8 {
+            let mut tx = BitcoinTransaction::new(
+                1,
+                vec![TransactionInput::new(
+                    OutPoint::new(dummy_txid(i), 0),
+                    Script::new(vec![0x51]),
+                    0xFFFFFFFF,
+                )],
+                0,
+            );
+            tx.outputs
+                .push(TransactionOutput::new(1_000, p2wpkh_script(i)));
+            txs.push(tx);
+        }
+        let mut block = dummy_header(7).to_bytes();
+        block.extend_from_slice(&CompactSize::new(txs.len() as u64).to_bytes());
+        for tx in &txs {
+            block.extend_from_slice(&tx.to_bytes());
+        }
+        let mut file = Vec::new();
+        for _ in 0..2 {
+            file.extend_from_slice(&[0xF9, 0xBE, 0xB4, 0xD9]);
+            file.extend_from_slice(&(block.len() as u32).to_le_bytes());
+            file.extend_from_slice(&block);
+        }
+        // A truncated record, as left behind by an interrupted write.
+        file.extend_from_slice(&[0xF9, 0xBE, 0xB4, 0xD9]);
+        file.extend_from_slice(&(block.len() as u32).to_le_bytes());
+        file.extend_from_slice(&block[..40]);
+        (file, txs)
+    }
+
+    #[test]
+    fn test_scan_block_file() {
+        let (data, txs) = block_file_bytes();
+        let path = std::env::temp_dir().join(format!("blk-scan-{}.dat", std::process::id()));
+        std::fs::write(&path, &data).unwrap();
+
+        let mut seen = Vec::new();
+        let records = block::scan_block_file(&path, |header, iter| {
+            assert_eq!(header, &dummy_header(7));
+            seen.extend(iter.map(Result::unwrap));
+        })
+        .unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(records, 2);
+        assert_eq!(seen.len(), 4);
+        assert_eq!(&seen[..2], &txs[..]);
+    }
+
+    #[cfg(feature = "memmap")]
+    #[test]
+    fn test_scan_block_file_mmap() {
+        let (data, txs) = block_file_bytes();
+        let path = std::env::temp_dir().join(format!("blk-mmap-{}.dat", std::process::id()));
+        std::fs::write(&path, &data).unwrap();
+
+        let mut seen = Vec::new();
+        // SAFETY: the file is private to this test and not touched until the
+        // scan returns.
+        let records = unsafe {
+            block::scan_block_file_mmap(&path, |_, iter| {
+                seen.extend(iter.map(Result::unwrap));
+            })
+        }
+        .unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(records, 2);
+        assert_eq!(&seen[2..], &txs[..]);
+    }
+
+    // Native P2WPKH example from BIP143.
+    const BIP143_P2WPKH_TX: &str = "0100000002fff7f7881a8099afa6940d42d1e7f6362bec38171ea3edf433541db4e4ad969f0000000000eeffffffef51e1b804cc89d182d279655c3aa89e815b1b309fe287d9b2b55d57b90ec68a0100000000ffffffff02202cb206000000001976a9148280b37df378db99f66f85c95a783a76ac7a6d5988ac9093510d000000001976a9143bde42dbee7e4dbe6a21b2d50ce2f0167faa815988ac11000000";
+
+    fn bip143_p2wpkh_tx() -> BitcoinTransaction {
+        BitcoinTransaction::from_bytes(&hex::decode(BIP143_P2WPKH_TX).unwrap())
+            .unwrap()
+            .0
+    }
+
+    fn bip143_script_code() -> Script {
+        Script::new(hex::decode("76a9141d0f172a0ecb48aee1be1f2687d2963ae33f71a188ac").unwrap())
+    }
+
+    #[test]
+    fn test_bip143_cache() {
+        let cache = bip143_p2wpkh_tx().bip143_cache();
+        assert_eq!(
+            hex::encode(cache.hash_prevouts),
+            "96b827c8483d4e9b96712b6713a7b68d6e8003a781feba36c31143470b4efd37"
+        );
+        assert_eq!(
+            hex::encode(cache.hash_sequence),
+            "52b0a642eea2fb7ae638c36f6252b6750293dbe574a806984b8e4d8548339a3b"
+        );
+        assert_eq!(
+            hex::encode(cache.hash_outputs),
+            "863ef3e1a92afbfdb97f31ad0fc7683ee943e9abcf2501590ff8f6551f47e5e5"
+        );
+    }
+
+    #[test]
+    fn test_segwit_sighash_cached_matches_uncached() {
+        let tx = bip143_p2wpkh_tx();
+        let cache = tx.bip143_cache();
+        let script_code = bip143_script_code();
+        let uncached = tx
+            .segwit_sighash(1, &script_code, 600_000_000, 0x01, None)
+            .unwrap();
+        let cached = tx
+            .segwit_sighash(1, &script_code, 600_000_000, 0x01, Some(&cache))
+            .unwrap();
+        assert_eq!(cached, uncached);
+        assert_eq!(
+            hex::encode(cached),
+            "c37af31116d1b27caf68aae9e3ac82f1477929014d5b917657d0eb49478cb670"
+        );
+        assert_eq!(
+            tx.segwit_sighash(2, &script_code, 600_000_000, 0x01, None),
+            Err(BitcoinError::InvalidFormat)
+        );
+    }
+
+    fn segwit_tx_with_witness_items(count: usize) -> Vec<u8> {
+        let mut input =
+            TransactionInput::new(OutPoint::new(dummy_txid(1), 0), Script::new(vec![]), 0);
+        input.witness = Witness::new(vec![vec![0x01]; count]);
+        let mut tx = BitcoinTransaction::new(2, vec![input], 0);
+        tx.outputs
+            .push(TransactionOutput::new(1_000, p2wpkh_script(1)));
+        tx.to_bytes()
+    }
+
+    #[test]
+    fn test_witness_stack_item_limit() {
+        let at_limit = segwit_tx_with_witness_items(100);
+        let over_limit = segwit_tx_with_witness_items(101);
+        let strict = ParseConfig::strict();
+
+        assert!(BitcoinTransaction::from_bytes_with_config(&at_limit, &strict).is_ok());
+        assert_eq!(
+            BitcoinTransaction::from_bytes_with_config(&over_limit, &strict),
+            Err(BitcoinError::InvalidFormat)
+        );
+        let (parsed, _) = BitcoinTransaction::from_bytes(&over_limit).unwrap();
+        assert_eq!(parsed.inputs[0].witness.len(), 101);
+    }
+
+    #[test]
+    fn test_witness_rejects_oversized_item() {
+        // One item declaring 0xFFFFFFFF bytes with only two present.
+        let bytes = [0x01, 0xFE, 0xFF, 0xFF, 0xFF, 0xFF, 0xAA, 0xBB];
+        assert_eq!(
+            Witness::from_bytes(&bytes),
+            Err(BitcoinError::InsufficientBytes)
+        );
+        assert_eq!(
+            Witness::from_bytes_with_config(&bytes, &ParseConfig::strict()),
+            Err(BitcoinError::InsufficientBytes)
+        );
+    }
+
+    #[test]
+    fn test_eq_ignoring_witness() {
+        let mut first = bip143_p2wpkh_tx();
+        first.inputs[1].witness = Witness::new(vec![vec![0x30; 71], vec![0x02; 33]]);
+        let mut second = first.clone();
+        second.inputs[1].witness = Witness::new(vec![vec![0x31; 72], vec![0x02; 33]]);
+
+        assert_ne!(first, second);
+        assert!(first.eq_ignoring_witness(&second));
+
+        second.inputs[1].sequence = 0;
+        assert!(!first.eq_ignoring_witness(&second));
+    }
+
+    fn p2pkh_script(byte: u8) -> Script {
+        let mut bytes = vec![0x76, 0xA9, 0x14];
+        bytes.extend_from_slice(&[byte; 20]);
+        bytes.extend_from_slice(&[0x88, 0xAC]);
+        Script::new(bytes)
+    }
+
+    fn p2sh_script(byte: u8) -> Script {
+        let mut bytes = vec![0xA9, 0x14];
+        bytes.extend_from_slice(&[byte; 20]);
+        bytes.push(0x87);
+        Script::new(bytes)
+    }
+
+    #[test]
+    fn test_output_pubkey_hashes() {
+        let mut tx = BitcoinTransaction::new(2, vec![], 0);
+        tx.outputs = vec![
+            TransactionOutput::new(1_000, p2pkh_script(0x01)),
+            TransactionOutput::new(1_000, p2sh_script(0x02)),
+            TransactionOutput::new(1_000, p2wpkh_script(0x03)),
+            TransactionOutput::new(0, Script::new(vec![0x6A, 0x01, 0xFF])),
+        ];
+        assert_eq!(tx.outputs[0].script_pubkey.script_type(), ScriptType::P2pkh);
+        assert_eq!(tx.outputs[1].script_pubkey.script_type(), ScriptType::P2sh);
+        assert_eq!(
+            tx.outputs[2].script_pubkey.script_type(),
+            ScriptType::P2wpkh
+        );
+        assert_eq!(
+            tx.outputs[3].script_pubkey.script_type(),
+            ScriptType::NullData
+        );
+        assert_eq!(tx.output_pubkey_hashes(), vec![[0x01; 20], [0x03; 20]]);
+    }
+
+    #[test]
+    fn test_max_tx_size() {
+        let bytes = hex::decode(BIP143_P2WPKH_TX).unwrap();
+        let mut config = ParseConfig::default();
+        assert_eq!(config.max_tx_size, 1_000_000);
+
+        config.max_tx_size = bytes.len();
+        assert!(BitcoinTransaction::from_bytes_with_config(&bytes, &config).is_ok());
+
+        config.max_tx_size = bytes.len() - 1;
+        assert_eq!(
+            BitcoinTransaction::from_bytes_with_config(&bytes, &config),
+            Err(BitcoinError::OversizedTransaction)
+        );
+        assert_eq!(
+            BitcoinTransaction::from_bytes_with_config(&bytes[..bytes.len() - 1], &config),
+            Err(BitcoinError::InsufficientBytes)
+        );
+    }
+
+    #[test]
+    fn test_block_roundtrip() {
+        let (_, txs) = block_file_bytes();
+        let mut segwit = bip143_p2wpkh_tx();
+        segwit.inputs[1].witness = Witness::new(vec![vec![0x30; 71], vec![0x02; 33]]);
+        let mut transactions = txs.clone();
+        transactions.push(segwit);
+
+        let body = block::serialize_block_body(&transactions);
+        assert_eq!(body[0], 3);
+
+        let block = block::Block::new(dummy_header(9), transactions);
+        let bytes = block.to_bytes();
+        assert_eq!(&bytes[80..], &body[..]);
+        let (parsed, consumed) = block::Block::from_bytes(&bytes).unwrap();
+        assert_eq!(parsed, block);
+        assert_eq!(consumed, bytes.len());
+    }
+
+    #[test]
+    fn test_version_message_roundtrip() {
+        let mut ipv4 = [0u8; 16];
+        ipv4[10] = 0xFF;
+        ipv4[11] = 0xFF;
+        ipv4[12..].copy_from_slice(&[127, 0, 0, 1]);
+        let msg = p2p::VersionMessage {
+            version: 70016,
+            services: 0x0409,
+            timestamp: 1_700_000_000,
+            addr_recv: p2p::NetAddress::new(0x0409, ipv4, 8333),
+            addr_from: p2p::NetAddress::new(0, [0u8; 16], 0),
+            nonce: 0x1234_5678_9ABC_DEF0,
+            user_agent: "/Satoshi:27.0.0/".to_string(),
+            start_height: 840_000,
+            relay: true,
+        };
+        let bytes = msg.to_bytes();
+        assert_eq!(bytes.len(), 4 + 8 + 8 + 26 + 26 + 8 + 17 + 4 + 1);
+        assert_eq!(&bytes[44..46], &8333u16.to_be_bytes());
+        let (parsed, consumed) = p2p::VersionMessage::from_bytes(&bytes).unwrap();
+        assert_eq!(parsed, msg);
+        assert_eq!(consumed, bytes.len());
+
+        let (parsed, consumed) =
+            p2p::VersionMessage::from_bytes(&bytes[..bytes.len() - 1]).unwrap();
+        assert!(parsed.relay);
+        assert_eq!(consumed, bytes.len() - 1);
+        for len in 0..bytes.len() - 1 {
+            assert_eq!(
+                p2p::VersionMessage::from_bytes(&bytes[..len]),
+                Err(BitcoinError::InsufficientBytes)
+            );
+        }
+    }
+
+    #[test]
+    fn test_input_builder_methods() {
+        let input = TransactionInput::new(OutPoint::new(dummy_txid(1), 0), Script::new(vec![]), 0)
+            .with_script_sig(Script::new(vec![0x51]))
+            .with_sequence(0xFFFFFFFE);
+        assert_eq!(input.script_sig, Script::new(vec![0x51]));
+        assert_eq!(input.sequence, 0xFFFFFFFE);
+    }
+
+    #[test]
+    fn test_map_input() {
+        let mut tx = bip143_p2wpkh_tx();
+        let original = tx.clone();
+        tx.map_input(1, |input| {
+            input.script_sig = Script::new(vec![0x00]);
+            input.sequence = 7;
+        })
+        .unwrap();
+        assert_eq!(tx.inputs[0], original.inputs[0]);
+        assert_eq!(tx.inputs[1].script_sig, Script::new(vec![0x00]));
+        assert_eq!(tx.inputs[1].sequence, 7);
+        assert_eq!(tx.map_input(2, |_| {}), Err(BitcoinError::InvalidFormat));
+    }
+
+    #[test]
+    fn test_script_instructions() {
+        let script = Script::new(vec![0x00, 0x02, 0xAA, 0xBB, 0x4C, 0x01, 0xCC, 0x76, 0x51]);
+        let instructions: Vec<_> = script.instructions().map(Result::unwrap).collect();
+        assert_eq!(
+            instructions,
+            vec![
+                script::Instruction::PushBytes(&[]),
+                script::Instruction::PushBytes(&[0xAA, 0xBB]),
+                script::Instruction::PushBytes(&[0xCC]),
+                script::Instruction::Op(0x76),
+                script::Instruction::Op(0x51),
+            ]
+        );
+        let truncated = Script::new(vec![0x76, 0x03, 0xAA]);
+        let mut iter = truncated.instructions();
+        assert_eq!(iter.next(), Some(Ok(script::Instruction::Op(0x76))));
+        assert_eq!(iter.next(), Some(Err(BitcoinError::InsufficientBytes)));
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn test_script_timelocks() {
+        // <500000> OP_CLTV OP_DROP <pubkey> OP_CHECKSIG
+        let mut bytes = vec![0x03, 0x20, 0xA1, 0x07, 0xB1, 0x75, 0x21];
+        bytes.extend_from_slice(&[0x02; 33]);
+        bytes.push(0xAC);
+        let cltv = Script::new(bytes);
+        assert_eq!(
+            cltv.timelocks(),
+            vec![script::TimelockConstraint::Cltv(500_000)]
+        );
+
+        // <2^39 - 1> OP_CLTV: a 5-byte operand, too long for arithmetic.
+        let five_byte = Script::new(vec![0x05, 0xFF, 0xFF, 0xFF, 0xFF, 0x7F, 0xB1]);
+        assert_eq!(
+            five_byte.timelocks(),
+            vec![script::TimelockConstraint::Cltv((1 << 39) - 1)]
+        );
+        assert_eq!(
+            five_byte
+                .instructions()
+                .next()
+                .unwrap()
+                .unwrap()
+                .as_number(),
+            None
+        );
+
+        // OP_16 OP_CSV OP_DROP OP_DUP OP_CLTV
+        let mixed = Script::new(vec![0x60, 0xB2, 0x75, 0x76, 0xB1]);
+        assert_eq!(mixed.timelocks(), vec![script::TimelockConstraint::Csv(16)]);
+    }
+
+    #[test]
+    fn test_txid_json_uses_display_order() {
+        let outpoint = OutPoint::new(dummy_txid(0xAB), 1);
+        let json = serde_json::to_string(&outpoint).unwrap();
+        assert_eq!(
+            json,
+            format!("{{\"txid\":\"ab{}\",\"vout\":1}}", "00".repeat(31))
+        );
+        let parsed: OutPoint = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, outpoint);
+
+        let tx = BitcoinTransaction::new(
+            1,
+            vec![TransactionInput::new(outpoint, Script::new(vec![]), 0)],
+            0,
+        );
+        let output = format!("{}", tx);
+        assert!(output.contains(&format!("Previous Output Txid: ab{}", "00".repeat(31))));
+    }
+
+    #[test]
+    fn test_txid_internal_hex_opt_in() {
+        #[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug)]
+        struct Raw {
+            #[serde(with = "txid_internal_hex")]
+            txid: Txid,
+        }
+        let raw = Raw {
+            txid: Txid(dummy_txid(0xAB)),
+        };
+        let json = serde_json::to_string(&raw).unwrap();
+        assert_eq!(json, format!("{{\"txid\":\"{}ab\"}}", "00".repeat(31)));
+        assert_eq!(serde_json::from_str::<Raw>(&json).unwrap(), raw);
+    }
+
+    #[test]
+    fn test_parsers_never_panic_on_mutated_input() {
+        let mut segwit = bip143_p2wpkh_tx();
+        segwit.inputs[1].witness = Witness::new(vec![vec![0x30; 71], vec![0x02; 33]]);
+        let seeds = [bip143_p2wpkh_tx().to_bytes(), segwit.to_bytes()];
+
+        // Deterministic xorshift so failures are reproducible.
+        let mut state: u64 = 0x9E37_79B9_7F4A_7C15;
+        let mut next = || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        for _ in 0..20_000 {
+            let mut bytes = seeds[(next() % 2) as usize].clone();
+            for _ in 0..(next() % 4 + 1) {
+                let i = next() as usize % bytes.len();
+                match next() % 3 {
+                    0 => bytes[i] = next() as u8,
+                    1 => bytes[i] = 0xFF,
+                    _ => bytes.truncate(i.max(1)),
+                }
+            }
+            if let Ok((_, consumed)) = BitcoinTransaction::from_bytes(&bytes) {
+                assert!(consumed <= bytes.len());
+            }
+            let _ = BitcoinTransaction::from_bytes_inputs_only(&bytes);
+        }
+    }
+
+    #[test]
+    fn test_truncation_at_every_boundary() {
+        let legacy = bip143_p2wpkh_tx();
+        let mut segwit = legacy.clone();
+        segwit.inputs[1].witness = Witness::new(vec![vec![0x30; 71], vec![0x02; 33]]);
+
+        for tx in [&legacy, &segwit] {
+            let bytes = tx.to_bytes();
+            for len in 0..bytes.len() {
+                assert_eq!(
+                    BitcoinTransaction::from_bytes(&bytes[..len]),
+                    Err(BitcoinError::InsufficientBytes),
+                    "prefix of {} bytes",
+                    len
+                );
+            }
+        }
+
+        let input_bytes = legacy.inputs[0].to_bytes();
+        for len in 0..input_bytes.len() {
+            assert_eq!(
+                TransactionInput::from_bytes(&input_bytes[..len]),
+                Err(BitcoinError::InsufficientBytes)
+            );
+        }
+        let output_bytes = legacy.outputs[0].to_bytes();
+        for len in 0..output_bytes.len() {
+            assert_eq!(
+                TransactionOutput::from_bytes(&output_bytes[..len]),
+                Err(BitcoinError::InsufficientBytes)
+            );
+        }
+        let inputs_only = legacy.to_bytes_inputs_only();
+        for len in 0..inputs_only.len() {
+            assert_eq!(
+                BitcoinTransaction::from_bytes_inputs_only(&inputs_only[..len]),
+                Err(BitcoinError::InsufficientBytes)
+            );
+        }
+    }
+
+    #[test]
+    fn test_all_legacy_sighashes_match_per_input() {
+        let tx = bip143_p2wpkh_tx();
+        let scripts = vec![p2pkh_script(0x01), p2pkh_script(0x02)];
+        for sighash_type in [0x01, 0x02, 0x03, 0x81] {
+            let all = tx.all_legacy_sighashes(&scripts, sighash_type).unwrap();
+            assert_eq!(all.len(), 2);
+            for (i, script) in scripts.iter().enumerate() {
+                assert_eq!(all[i], tx.legacy_sighash(i, script, sighash_type).unwrap());
+            }
+            assert_ne!(all[0], all[1]);
+        }
+        assert_eq!(
+            tx.all_legacy_sighashes(&scripts[..1], 0x01),
+            Err(BitcoinError::InvalidFormat)
+        );
+        assert_eq!(
+            tx.legacy_sighash(2, &scripts[0], 0x01),
+            Err(BitcoinError::InvalidFormat)
+        );
+    }
+
+    #[test]
+    fn test_legacy_sighash_single_without_output() {
+        let mut tx = bip143_p2wpkh_tx();
+        tx.outputs.truncate(1);
+        let mut one = [0u8; 32];
+        one[0] = 1;
+        assert_eq!(
+            tx.legacy_sighash(1, &p2pkh_script(0x01), 0x03).unwrap(),
+            one
+        );
+        assert_ne!(
+            tx.legacy_sighash(0, &p2pkh_script(0x01), 0x03).unwrap(),
+            one
+        );
+    }
+
+    #[test]
+    fn test_annotated_hex() {
+        let mut input = TransactionInput::new(
+            OutPoint::new(dummy_txid(0xAB), 1),
+            Script::new(vec![]),
+            0xFFFFFFFD,
+        );
+        input.witness = Witness::new(vec![vec![0xCC, 0xDD]]);
+        let mut tx = BitcoinTransaction::new(2, vec![input], 0);
+        tx.outputs
+            .push(TransactionOutput::new(1_000, Script::new(vec![0x51])));
+
+        let expected = format!(
+            "0000  version                        02000000
+0004  marker                         00
+0005  flag                           01
+0006  input count                    01
+0007  input[0] txid                  {}ab
+0027  input[0] vout                  01000000
+002b  input[0] scriptSig length      00
+002c  input[0] sequence              fdffffff
+0030  output count                   01
+0031  output[0] value                e803000000000000
+0039  output[0] scriptPubKey length  01
+003a  output[0] scriptPubKey         51
+003b  witness[0] item count          01
+003c  witness[0][0] length           02
+003d  witness[0][0]                  ccdd
+003f  lock_time                      00000000
+",
+            "00".repeat(31)
+        );
+        assert_eq!(tx.annotated_hex(), expected);
+    }
+
+    #[test]
+    fn test_script_witness_version() {
+        let v0 = p2wpkh_script(0x01);
+        let mut v1_bytes = vec![0x51, 0x20];
+        v1_bytes.extend_from_slice(&[0x02; 32]);
+        let v1 = Script::new(v1_bytes);
+        let legacy = p2pkh_script(0x01);
+
+        assert_eq!(v0.witness_version(), Some(0));
+        assert_eq!(v1.witness_version(), Some(1));
+        assert_eq!(legacy.witness_version(), None);
+        assert_eq!(Script::new(vec![0x51, 0x01, 0x00]).witness_version(), None);
+
+        assert!(v1.starts_with(&[0x51, 0x20]));
+        assert!(!legacy.starts_with(&[0x51]));
+        assert!(legacy.starts_with(&[]));
+    }
+
+    #[test]
+    fn test_output_mutation() {
+        let mut tx = bip143_p2wpkh_tx();
+        let change = TransactionOutput::new(90_000, p2wpkh_script(0x09));
+        tx.replace_output(1, change.clone()).unwrap();
+        assert_eq!(tx.outputs[1], change);
+        assert_eq!(
+            tx.replace_output(2, change.clone()),
+            Err(BitcoinError::InvalidFormat)
+        );
+
+        let extra = TransactionOutput::new(1_000, p2pkh_script(0x07));
+        tx.add_output_at(0, extra.clone()).unwrap();
+        assert_eq!(tx.outputs[0], extra);
+        assert_eq!(tx.outputs[2], change);
+        tx.add_output_at(3, extra.clone()).unwrap();
+        assert_eq!(tx.outputs.len(), 4);
+        assert_eq!(
+            tx.add_output_at(5, extra.clone()),
+            Err(BitcoinError::InvalidFormat)
+        );
+
+        assert_eq!(tx.remove_output(3), Ok(extra));
+        assert_eq!(tx.remove_output(3), Err(BitcoinError::InvalidFormat));
+        assert_eq!(tx.outputs.len(), 3);
+    }
+
+    #[test]
+    fn test_zero_input_tx_uses_legacy_encoding() {
+        let mut tx = BitcoinTransaction::new(2, vec![], 0);
+        tx.outputs
+            .push(TransactionOutput::new(1_000, p2wpkh_script(0x01)));
+
+        let bytes = tx.to_bytes();
+        assert_eq!(bytes, tx.to_bytes_without_witness());
+        assert_eq!(&bytes[4..6], &[0x00, 0x01]);
+
+        let (parsed, consumed) = BitcoinTransaction::from_bytes_without_witness(&bytes).unwrap();
+        assert_eq!(parsed, tx);
+        assert_eq!(consumed, bytes.len());
+        // The segwit-aware parser reads the empty input list as marker and flag.
+        assert_ne!(
+            BitcoinTransaction::from_bytes(&bytes),
+            Ok((tx, bytes.len()))
+        );
+    }
+
+    // The first bitcoin payment, from block 170.
+    const BLOCK_170_TX: &str = "0100000001c997a5e56e104102fa209c6a852dd90660a20b2d9c352423edce25857fcd3704000000004847304402204e45e16932b8af514961a1d3a1a25fdf3f4f7732e9d624c6c61548ab5fb8cd410220181522ec8eca07de4860a4acdd12909d831cc56cbbac4622082221a8768d1d0901ffffffff0200ca9a3b00000000434104ae1a62fe09c5f51b13905f07f06b99a2f7159b2225f374cd378d71302fa28414e7aab37397f554a7df5f142c21c1b7303b8a0626f1baded5c72a704f7e6cd84cac00286bee0000000043410411db93e1dcdb8a016b49840f8c53bc1eb68a382e97b1482ecad7b148a6909a5cb2e0eaddfb84ccf9744464f82e160bfa9b8b64f9d4c03f999b8643f656b412a3ac00000000";
+    const BLOCK_170_TXID: &str = "f4184fc596403b9d638783cf57adfe4c75c605f6356fbc91338530e9831e9e16";
+
+    #[test]
+    fn test_txid_from_raw_hex() {
+        let txid = txid_from_raw_hex(BLOCK_170_TX).unwrap();
+        assert_eq!(txid.to_display_string(), BLOCK_170_TXID);
+
+        let (tx, _) = BitcoinTransaction::from_bytes(&hex::decode(BLOCK_170_TX).unwrap()).unwrap();
+        assert_eq!(tx.txid(), txid);
+        assert_eq!(tx.wtxid(), txid);
+
+        let mut segwit = tx.clone();
+        segwit.inputs[0].witness.push(vec![0x01]);
+        let segwit_hex = hex::encode(segwit.to_bytes());
+        assert_eq!(txid_from_raw_hex(&segwit_hex), Ok(txid.clone()));
+        assert_ne!(segwit.wtxid(), txid);
+
+        assert_eq!(txid_from_raw_hex("zz"), Err(BitcoinError::InvalidFormat));
+        assert_eq!(
+            txid_from_raw_hex(&format!("{}00", BLOCK_170_TX)),
+            Err(BitcoinError::InvalidFormat)
+        );
+    }
+
+    #[test]
+    fn test_custom_sha256_engine() {
+        use hashes::Sha256Engine;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+        struct CountingEngine;
+        impl Sha256Engine for CountingEngine {
+            fn hash(data: &[u8]) -> [u8; 32] {
+                CALLS.fetch_add(1, Ordering::SeqCst);
+                hashes::DefaultSha256::hash(data)
+            }
+        }
+
+        struct MockEngine;
+        impl Sha256Engine for MockEngine {
+            fn hash(data: &[u8]) -> [u8; 32] {
+                [data.len() as u8; 32]
+            }
+        }
+
+        assert_eq!(hashes::double_sha256_with::<MockEngine>(b"abc"), [32; 32]);
+
+        let (tx, _) = BitcoinTransaction::from_bytes(&hex::decode(BLOCK_170_TX).unwrap()).unwrap();
+        assert_eq!(tx.txid_with::<CountingEngine>(), tx.txid());
+        assert_eq!(CALLS.load(Ordering::SeqCst), 2);
+        assert_eq!(tx.txid_with::<MockEngine>(), Txid([32; 32]));
+    }
+
+    #[test]
+    fn test_pay_to_anchor() {
+        let anchor = Script::new(vec![0x51, 0x02, 0x4e, 0x73]);
+        assert_eq!(anchor.script_type(), ScriptType::P2a);
+        assert!(anchor.is_ephemeral_anchor());
+        assert_eq!(anchor.witness_version(), Some(1));
+
+        let other_program = Script::new(vec![0x51, 0x02, 0x4e, 0x74]);
+        assert_eq!(other_program.script_type(), ScriptType::NonStandard);
+        assert!(!other_program.is_ephemeral_anchor());
+        assert!(!p2wpkh_script(1).is_ephemeral_anchor());
+    }
+
+    #[test]
+    fn test_sequence_fingerprint() {
+        use policy::{RbfPolicy, SequenceFingerprint};
+
+        let input = |sequence| {
+            TransactionInput::new(
+                OutPoint::new(dummy_txid(1), 0),
+                Script::new(vec![]),
+                sequence,
+            )
+        };
+        let mut tx = BitcoinTransaction::new(
+            2,
+            vec![input(SEQUENCE_FINAL), input(0xFFFF_FFFE), input(144)],
+            0,
+        );
+        assert_eq!(
+            tx.sequence_fingerprint(),
+            SequenceFingerprint {
+                all_equal: false,
+                rbf: RbfPolicy::Replaceable,
+                relative_lock_times: true,
+            }
+        );
+
+        tx.version = 1;
+        assert!(!tx.sequence_fingerprint().relative_lock_times);
+
+        tx.inputs.truncate(2);
+        let fingerprint = tx.sequence_fingerprint();
+        assert_eq!(fingerprint.rbf, RbfPolicy::NonReplaceable);
+        assert!(!fingerprint.all_equal);
+
+        tx.inputs.truncate(1);
+        let fingerprint = tx.sequence_fingerprint();
+        assert_eq!(fingerprint.rbf, RbfPolicy::Final);
+        assert!(fingerprint.all_equal);
+    }
+
+    #[test]
+    fn test_validate_segwit_input_shapes() {
+        let signature_and_key = vec![der_signature(&[0x01; 32], &[0x02; 32]), vec![0x02; 33]];
+        let mut input =
+            TransactionInput::new(OutPoint::new(dummy_txid(1), 0), Script::new(vec![]), 0);
+        input.witness = Witness::new(signature_and_key.clone());
+        let mut tx = BitcoinTransaction::new(2, vec![input], 0);
+        assert_eq!(
+            tx.validate_segwit_input_shapes(&[ScriptType::P2wpkh]),
+            Ok(())
+        );
+
+        tx.inputs[0].witness.items.pop();
+        assert_eq!(
+            tx.validate_segwit_input_shapes(&[ScriptType::P2wpkh]),
+            Err(BitcoinError::InvalidFormat)
+        );
+
+        tx.inputs[0].witness = Witness::new(signature_and_key);
+        tx.inputs[0].script_sig = Script::new(vec![0x00]);
+        assert_eq!(
+            tx.validate_segwit_input_shapes(&[ScriptType::P2wpkh]),
+            Err(BitcoinError::InvalidFormat)
+        );
+
+        let mut wrapped = vec![0x16];
+        wrapped.extend_from_slice(&p2wpkh_script(0x33).bytes);
+        tx.inputs[0].script_sig = Script::new(wrapped);
+        assert_eq!(tx.validate_segwit_input_shapes(&[ScriptType::P2sh]), Ok(()));
+        assert_eq!(
+            tx.validate_segwit_input_shapes(&[]),
+            Err(BitcoinError::InvalidFormat)
+        );
+        // Native segwit types reject a scriptSig, even one with a witness.
+        for script_type in [ScriptType::P2wsh, ScriptType::P2tr] {
+            assert_eq!(
+                tx.validate_segwit_input_shapes(&[script_type]),
+                Err(BitcoinError::InvalidFormat)
+            );
+        }
+
+        tx.inputs[0].witness = Witness::new(vec![]);
+        assert_eq!(
+            tx.validate_segwit_input_shapes(&[ScriptType::P2sh]),
+            Err(BitcoinError::InvalidFormat)
+        );
+
+        // A legacy P2SH spend carries no witness.
+        tx.inputs[0].script_sig = Script::new(vec![0x00, 0x01, 0x51]);
+        assert_eq!(tx.validate_segwit_input_shapes(&[ScriptType::P2sh]), Ok(()));
+    }
+
+    #[test]
+    fn test_to_bytes_no_scripts() {
+        let (tx, _) = BitcoinTransaction::from_bytes(&hex::decode(BLOCK_170_TX).unwrap()).unwrap();
+        assert!(!tx.inputs[0].script_sig.is_empty());
+
+        let mut blanked = tx.clone();
+        blanked.inputs[0].script_sig = Script::new(vec![]);
+        assert_eq!(tx.to_bytes_no_scripts(), blanked.to_bytes());
+        assert_ne!(tx.to_bytes_no_scripts(), tx.to_bytes_without_witness());
+        assert_eq!(
+            tx.to_bytes_no_scripts().len(),
+            tx.to_bytes_without_witness().len() - tx.inputs[0].script_sig.len()
+        );
+    }
+
+    #[test]
+    fn test_invalid_transaction_vectors() {
+        let cases: Vec<serde_json::Value> =
+            serde_json::from_str(include_str!("data/invalid_transactions.json")).unwrap();
+        assert!(!cases.is_empty());
+        for case in &cases {
+            let description = case["description"].as_str().unwrap();
+            let bytes = hex::decode(case["hex"].as_str().unwrap()).unwrap();
+            let expected = match case["expected_error"].as_str().unwrap() {
+                "InsufficientBytes" => BitcoinError::InsufficientBytes,
+                "InvalidFormat" => BitcoinError::InvalidFormat,
+                "OversizedTransaction" => BitcoinError::OversizedTransaction,
+                other => panic!("unknown error {other} in case {description:?}"),
+            };
+            let mut config = ParseConfig::default();
+            if let Some(max_tx_size) = case["max_tx_size"].as_u64() {
+                config.max_tx_size = max_tx_size as usize;
+            }
+            assert_eq!(
+                BitcoinTransaction::from_bytes_with_config(&bytes, &config),
+                Err(expected),
+                "{description}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_check_bip69() {
+        use policy::Bip69Violation;
+
+        // Txids compare in display order, so the last internal byte decides.
+        let mut low = [0xFF; 32];
+        low[31] = 0x01;
+        let high = dummy_txid(0x02);
+        let input = |txid: [u8; 32], vout| {
+            TransactionInput::new(
+                OutPoint::new(txid, vout),
+                Script::new(vec![]),
+                SEQUENCE_FINAL,
+            )
+        };
+
+        let mut tx =
+            BitcoinTransaction::new(2, vec![input(low, 1), input(high, 0), input(high, 3)], 0);
+        tx.outputs = vec![
+            TransactionOutput::new(1_000, p2wpkh_script(0x02)),
+            TransactionOutput::new(1_000, p2wpkh_script(0x03)),
+            TransactionOutput::new(5_000, p2wpkh_script(0x01)),
+        ];
+        assert_eq!(tx.check_bip69(), Ok(()));
+
+        tx.outputs.swap(0, 1);
+        assert_eq!(
+            tx.check_bip69(),
+            Err(Bip69Violation::OutputOutOfOrder { index: 1 })
+        );
+
+        tx.inputs.swap(1, 2);
+        assert_eq!(
+            tx.check_bip69(),
+            Err(Bip69Violation::InputOutOfOrder { index: 2 })
+        );
+    }
+
+    #[test]
+    fn test_txid_streaming() {
+        let (tx, _) = BitcoinTransaction::from_bytes(&hex::decode(BLOCK_170_TX).unwrap()).unwrap();
+        assert_eq!(tx.txid_streaming(), tx.txid());
+        assert_eq!(tx.txid_streaming().to_display_string(), BLOCK_170_TXID);
+
+        let (segwit, _) = BitcoinTransaction::from_bytes(&segwit_tx_with_witness_items(2)).unwrap();
+        assert!(segwit.has_witness());
+        assert_eq!(segwit.txid_streaming(), segwit.txid());
+
+        let mut written = Vec::new();
+        segwit.to_writer(&mut written).unwrap();
+        assert_eq!(written, segwit.to_bytes());
+    }
+
+    #[test]
+    fn test_op_return_data() {
+        let payload = [0xAB; 80];
+        let mut bytes = vec![0x6A, 0x4C, 80];
+        bytes.extend_from_slice(&payload);
+        let script = Script::new(bytes);
+        assert_eq!(script.op_return_data(), Some(&payload[..]));
+
+        let mut two_pushes = vec![0x6A, 0x02, 0x01, 0x02, 0x01, 0x03];
+        assert_eq!(
+            Script::new(two_pushes.clone()).op_return_data(),
+            Some(&[0x01, 0x02][..])
+        );
+        two_pushes.truncate(1);
+        assert_eq!(Script::new(two_pushes).op_return_data(), Some(&[][..]));
+
+        assert_eq!(Script::new(vec![0x6A, 0x05, 0x01]).op_return_data(), None);
+        assert_eq!(p2wpkh_script(1).op_return_data(), None);
+    }
+
+    #[test]
+    fn test_output_address() {
+        let mut program = vec![0x00, 0x14];
+        program.extend(hex::decode("751e76e8199196d454941c45d1b3a323f1433bd6").unwrap());
+        let output = TransactionOutput::new(1_000, Script::new(program));
+        assert_eq!(
+            output.address(Network::Bitcoin).as_deref(),
+            Some("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4")
+        );
+        assert_eq!(
+            output.address(Network::Testnet).as_deref(),
+            Some("tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx")
+        );
+
+        let mut taproot = vec![0x51, 0x20];
+        taproot.extend(
+            hex::decode("79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798")
+                .unwrap(),
+        );
+        assert_eq!(
+            Script::new(taproot).to_address(Network::Bitcoin).as_deref(),
+            Some("bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0")
+        );
+        assert_eq!(
+            Script::new(vec![0x51, 0x02, 0x4e, 0x73])
+                .to_address(Network::Bitcoin)
+                .as_deref(),
+            Some("bc1pfeessrawgf")
+        );
+        assert_eq!(
+            p2pkh_script(0x00).to_address(Network::Bitcoin).as_deref(),
+            Some("1111111111111111111114oLvT2")
+        );
+
+        let op_return = TransactionOutput::new(0, Script::new(vec![0x6A, 0x01, 0x01]));
+        assert_eq!(op_return.address(Network::Bitcoin), None);
+
+        let bad_v0 = Script::new(vec![0x00, 0x03, 0xAA, 0xBB, 0xCC]);
+        assert_eq!(bad_v0.to_address(Network::Bitcoin), None);
+    }
+
+    #[test]
+    fn test_field_ranges_cover_serialization() {
+        let bytes = segwit_tx_with_witness_items(2);
+        let (tx, _) = BitcoinTransaction::from_bytes(&bytes).unwrap();
+        let ranges = tx.field_ranges();
+
+        let mut rebuilt = Vec::new();
+        for (label, range) in &ranges {
+            assert_eq!(range.start, rebuilt.len(), "gap or overlap at {label}");
+            rebuilt.extend_from_slice(&bytes[range.clone()]);
+        }
+        assert_eq!(rebuilt, bytes);
+
+        let labels: Vec<&str> = ranges.iter().map(|(label, _)| label.as_str()).collect();
+        assert_eq!(&labels[..3], ["version", "marker", "flag"]);
+        assert!(labels.contains(&"witness[0][1]"));
+        assert_eq!(ranges.last().unwrap().1, bytes.len() - 4..bytes.len());
+    }
+
+    #[test]
+    fn test_cfilter_roundtrip() {
+        let msg = p2p::CFilterMessage {
+            filter_type: 0,
+            block_hash: dummy_header(1).block_hash(),
+            filter: vec![0x02, 0x9A, 0x4B, 0x30],
+        };
+        let bytes = msg.to_bytes();
+        assert_eq!(bytes.len(), 1 + 32 + 1 + 4);
+        assert_eq!(
+            p2p::CFilterMessage::from_bytes(&bytes),
+            Ok((msg, bytes.len()))
+        );
+        assert_eq!(
+            p2p::CFilterMessage::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(BitcoinError::InsufficientBytes)
+        );
+    }
+
+    #[test]
+    fn test_cfheaders_roundtrip() {
+        let msg = p2p::CFHeadersMessage {
+            filter_type: 0,
+            stop_hash: dummy_header(2).block_hash(),
+            previous_filter_header: [0x11; 32],
+            filter_hashes: vec![[0x22; 32], [0x33; 32]],
+        };
+        let bytes = msg.to_bytes();
+        assert_eq!(bytes.len(), 1 + 32 + 32 + 1 + 64);
+        assert_eq!(
+            p2p::CFHeadersMessage::from_bytes(&bytes),
+            Ok((msg, bytes.len()))
+        );
+        assert_eq!(
+            p2p::CFHeadersMessage::from_bytes(&bytes[..40]),
+            Err(BitcoinError::InsufficientBytes)
+        );
+        assert_eq!(
+            p2p::CFHeadersMessage::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(BitcoinError::InsufficientBytes)
+        );
+    }
+
+    #[test]
+    fn test_compact_size_display_and_deref() {
+        let size = CompactSize::new(515);
+        assert_eq!(size.to_string(), "515");
+        assert!(*size > 252);
+        assert_eq!(*size as usize + 1, 516);
+        assert!(CompactSize::new(1) < CompactSize::new(2));
+
+        let (parsed, _) = CompactSize::from_bytes(&[0xFD, 0x03, 0x02]).unwrap();
+        assert_eq!(*parsed, 515);
+    }
+
+    #[test]
+    fn test_is_witness_malleation() {
+        let (first, _) = BitcoinTransaction::from_bytes(&segwit_tx_with_witness_items(2)).unwrap();
+        let mut second = first.clone();
+        second.inputs[0].witness = Witness::new(vec![vec![0x02], vec![0x01]]);
+
+        assert!(is_witness_malleation(&first, &second));
+        assert!(!is_witness_malleation(&first, &first));
+
+        let mut different = second.clone();
+        different.lock_time = 1;
+        assert!(!is_witness_malleation(&first, &different));
+    }
+
+    #[test]
+    fn test_sighash_anyonecanpay_commits_to_one_input() {
+        use sighash::{SIGHASH_ALL, SIGHASH_ANYONECANPAY};
+
+        let tx = bip143_p2wpkh_tx();
+        let script_code = bip143_script_code();
+        let acp = SIGHASH_ALL | SIGHASH_ANYONECANPAY;
+        let legacy = |tx: &BitcoinTransaction, sighash_type| {
+            tx.legacy_sighash(1, &script_code, sighash_type).unwrap()
+        };
+        let segwit = |tx: &BitcoinTransaction, sighash_type| {
+            tx.segwit_sighash(1, &script_code, 600_000_000, sighash_type, None)
+                .unwrap()
+        };
+        assert_ne!(legacy(&tx, acp), legacy(&tx, SIGHASH_ALL));
+        assert_ne!(segwit(&tx, acp), segwit(&tx, SIGHASH_ALL));
+
+        // Changing the other input only affects the SIGHASH_ALL hashes.
+        let mut other_input_changed = tx.clone();
+        other_input_changed.inputs[0].previous_output.vout = 7;
+        other_input_changed.inputs[0].sequence = 0;
+        assert_eq!(legacy(&other_input_changed, acp), legacy(&tx, acp));
+        assert_eq!(segwit(&other_input_changed, acp), segwit(&tx, acp));
+        assert_ne!(
+            legacy(&other_input_changed, SIGHASH_ALL),
+            legacy(&tx, SIGHASH_ALL)
+        );
+        assert_ne!(
+            segwit(&other_input_changed, SIGHASH_ALL),
+            segwit(&tx, SIGHASH_ALL)
+        );
+    }
+
+    #[test]
+    fn test_meets_min_relay_fee() {
+        let (tx, _) = BitcoinTransaction::from_bytes(&segwit_tx_with_witness_items(2)).unwrap();
+        let rate = FeeRate::DEFAULT_MIN_RELAY;
+        let required = rate.fee_for(tx.weight());
+        assert_eq!(required, Amount::from_sat(tx.weight().div_ceil(4) as u64));
+
+        assert!(tx.meets_min_relay_fee(required, rate));
+        assert!(tx.meets_min_relay_fee(Amount::from_sat(required.to_sat() + 1), rate));
+        assert!(!tx.meets_min_relay_fee(Amount::from_sat(required.to_sat() - 1), rate));
+        assert!(!tx.meets_min_relay_fee(required, FeeRate::from_sat_per_vb(2)));
+        assert_eq!(FeeRate::from_sat_per_vb(1), rate);
+    }
+
+    #[test]
+    fn test_input_outpoint_accessors() {
+        fn vouts<T: AsRef<OutPoint>>(items: &[T]) -> Vec<u32> {
+            items.iter().map(|item| item.as_ref().vout).collect()
+        }
+
+        let inputs: Vec<TransactionInput> = (0..3)
+            .map(|vout| {
+                TransactionInput::new(OutPoint::new(dummy_txid(1), vout), Script::new(vec![]), 0)
+            })
+            .collect();
+        assert_eq!(inputs[2].outpoint(), &inputs[2].previous_output);
+        assert_eq!(vouts(&inputs), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_block_locator_roundtrip() {
+        let msg = p2p::BlockLocatorMessage {
+            version: 70016,
+            locator_hashes: vec![dummy_header(2).block_hash(), dummy_header(1).block_hash()],
+            stop_hash: [0u8; 32],
+        };
+        let bytes = msg.to_bytes();
+        assert_eq!(bytes.len(), 4 + 1 + 64 + 32);
+        assert_eq!(&bytes[..5], &[0x80, 0x11, 0x01, 0x00, 0x02]);
+        assert_eq!(
+            p2p::BlockLocatorMessage::from_bytes(&bytes),
+            Ok((msg, bytes.len()))
+        );
+        assert_eq!(
+            p2p::BlockLocatorMessage::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(BitcoinError::InsufficientBytes)
+        );
+    }
+
+    #[test]
+    fn test_p2wpkh_script_code() {
+        let hash: [u8; 20] = hex::decode("1d0f172a0ecb48aee1be1f2687d2963ae33f71a1")
+            .unwrap()
+            .try_into()
+            .unwrap();
+        let script_code = Script::p2wpkh_script_code(&hash);
+        assert_eq!(script_code, bip143_script_code());
+        assert_eq!(
+            hex::encode(script_code.to_bytes()),
+            "1976a9141d0f172a0ecb48aee1be1f2687d2963ae33f71a188ac"
+        );
+
+        let mut witness_program = vec![0x00, 0x14];
+        witness_program.extend_from_slice(&hash);
+        let pubkey_hash = Script::new(witness_program).pubkey_hash().unwrap();
+        assert_eq!(Script::p2wpkh_script_code(&pubkey_hash), script_code);
+    }
+
+    /// A minimal non-human-readable serde format that only handles tuples of
+    /// bytes, enough to exercise the binary encoding of `Txid`.
+    mod binary {
+        use serde::de::value::{Error, SeqDeserializer};
+        use serde::ser::{self, Impossible, Serialize};
+        use serde::{Deserializer, Serializer, forward_to_deserialize_any};
+
+        pub struct Bytes(pub Vec<u8>);
+
+        fn unsupported<T>() -> Result<T, Error> {
+            Err(ser::Error::custom("unsupported"))
+        }
+
+        impl Serializer for &mut Bytes {
+            type Ok = ();
+            type Error = Error;
+            type SerializeSeq = Impossible<(), Error>;
+            type SerializeTuple = Self;
+            type SerializeTupleStruct = Impossible<(), Error>;
+            type SerializeTupleVariant = Impossible<(), Error>;
+            type SerializeMap = Impossible<(), Error>;
+            type SerializeStruct = Impossible<(), Error>;
+            type SerializeStructVariant = Impossible<(), Error>;
+
+            fn is_human_readable(&self) -> bool {
+                false
+            }
+            fn serialize_u8(self, v: u8) -> Result<(), Error> {
+                self.0.push(v);
+                Ok(())
+            }
+            fn serialize_tuple(self, _: usize) -> Result<Self, Error> {
+                Ok(self)
+            }
+            fn serialize_bool(self, _: bool) -> Result<(), Error> {
+                unsupported()
+            }
+            fn serialize_i8(self, _: i8) -> Result<(), Error> {
+                unsupported()
+            }
+            fn serialize_i16(self, _: i16) -> Result<(), Error> {
+                unsupported()
+            }
+            fn serialize_i32(self, _: i32) -> Result<(), Error> {
+                unsupported()
+            }
+            fn serialize_i64(self, _: i64) -> Result<(), Error> {
+                unsupported()
+            }
+            fn serialize_u16(self, _: u16) -> Result<(), Error> {
+                unsupported()
+            }
+            fn serialize_u32(self, _: u32) -> Result<(), Error> {
+                unsupported()
+            }
+            fn serialize_u64(self, _: u64) -> Result<(), Error> {
+                unsupported()
+            }
+            fn serialize_f32(self, _: f32) -> Result<(), Error> {
+                unsupported()
+            }
+            fn serialize_f64(self, _: f64) -> Result<(), Error> {
+                unsupported()
+            }
+            fn serialize_char(self, _: char) -> Result<(), Error> {
+                unsupported()
+            }
+            fn serialize_str(self, _: &str) -> Result<(), Error> {
+                unsupported()
+            }
+            fn serialize_bytes(self, _: &[u8]) -> Result<(), Error> {
+                unsupported()
+            }
+            fn serialize_none(self) -> Result<(), Error> {
+                unsupported()
+            }
+            fn serialize_some<T: ?Sized + Serialize>(self, _: &T) -> Result<(), Error> {
+                unsupported()
+            }
+            fn serialize_unit(self) -> Result<(), Error> {
+                unsupported()
+            }
+            fn serialize_unit_struct(self, _: &'static str) -> Result<(), Error> {
+                unsupported()
+            }
+            fn serialize_unit_variant(
+                self,
+                _: &'static str,
+                _: u32,
+                _: &'static str,
+            ) -> Result<(), Error> {
+                unsupported()
+            }
+            fn serialize_newtype_struct<T: ?Sized + Serialize>(
+                self,
+                _: &'static str,
+                _: &T,
+            ) -> Result<(), Error> {
+                unsupported()
+            }
+            fn serialize_newtype_variant<T: ?Sized + Serialize>(
+                self,
+                _: &'static str,
+                _: u32,
+                _: &'static str,
+                _: &T,
+            ) -> Result<(), Error> {
+                unsupported()
+            }
+            fn serialize_seq(self, _: Option<usize>) -> Result<Self::SerializeSeq, Error> {
+                unsupported()
+            }
+            fn serialize_tuple_struct(
+                self,
+                _: &'static str,
+                _: usize,
+            ) -> Result<Self::SerializeTupleStruct, Error> {
+                unsupported()
+            }
+            fn serialize_tuple_variant(
+                self,
+                _: &'static str,
+                _: u32,
+                _: &'static str,
+                _: usize,
+            ) -> Result<Self::SerializeTupleVariant, Error> {
+                unsupported()
+            }
+            fn serialize_map(self, _: Option<usize>) -> Result<Self::SerializeMap, Error> {
+                unsupported()
+            }
+            fn serialize_struct(
+                self,
+                _: &'static str,
+                _: usize,
+            ) -> Result<Self::SerializeStruct, Error> {
+                unsupported()
+            }
+            fn serialize_struct_variant(
+                self,
+                _: &'static str,
+                _: u32,
+                _: &'static str,
+                _: usize,
+            ) -> Result<Self::SerializeStructVariant, Error> {
+                unsupported()
+            }
+        }
+
+        impl ser::SerializeTuple for &mut Bytes {
+            type Ok = ();
+            type Error = Error;
+
+            fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+                value.serialize(&mut **self)
+            }
+            fn end(self) -> Result<(), Error> {
+                Ok(())
+            }
+        }
+
+        impl<'de> Deserializer<'de> for Bytes {
+            type Error = Error;
+
+            fn deserialize_any<V: serde::de::Visitor<'de>>(
+                self,
+                visitor: V,
+            ) -> Result<V::Value, Error> {
+                visitor.visit_seq(SeqDeserializer::new(self.0.into_iter()))
+            }
+            fn is_human_readable(&self) -> bool {
+                false
+            }
+
+            forward_to_deserialize_any! {
+                bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+                bytes byte_buf option unit unit_struct newtype_struct seq tuple
+                tuple_struct map struct enum identifier ignored_any
+            }
+        }
+    }
+
+    #[test]
+    fn test_txid_binary_serialization_is_raw() {
+        use serde::{Deserialize, Serialize};
+
+        let txid = Txid(dummy_txid(0xAB));
+        let mut bytes = binary::Bytes(Vec::new());
+        txid.serialize(&mut bytes).unwrap();
+        assert_eq!(bytes.0, dummy_txid(0xAB).to_vec());
+        assert_eq!(Txid::deserialize(bytes).unwrap(), txid);
+
+        let mut internal = binary::Bytes(Vec::new());
+        txid_internal_hex::serialize(&txid, &mut internal).unwrap();
+        assert_eq!(internal.0, dummy_txid(0xAB).to_vec());
+        assert_eq!(txid_internal_hex::deserialize(internal).unwrap(), txid);
+
+        // JSON keeps the 64-character display hex.
+        let json = serde_json::to_string(&txid).unwrap();
+        assert_eq!(json, format!("\"{}\"", txid.to_display_string()));
+        assert_eq!(serde_json::from_str::<Txid>(&json).unwrap(), txid);
+    }
+
+    #[test]
+    fn test_standardness_flags_sighash_single_without_output() {
+        let signed_script_sig = |sighash_type: u8| {
+            let mut signature = der_signature(&[0x11; 32], &[0x22; 32]);
+            signature.push(sighash_type);
+            let mut bytes = vec![signature.len() as u8];
+            bytes.extend_from_slice(&signature);
+            bytes.push(33);
+            bytes.extend_from_slice(&[0x02; 33]);
+            Script::new(bytes)
+        };
+        let input = |vout, sighash_type| {
+            TransactionInput::new(
+                OutPoint::new(dummy_txid(1), vout),
+                signed_script_sig(sighash_type),
+                SEQUENCE_FINAL,
+            )
+        };
+        let mut tx =
+            BitcoinTransaction::new(2, vec![input(0, 0x03), input(1, 0x83), input(2, 0x01)], 0);
+        tx.outputs
+            .push(TransactionOutput::new(50_000, p2wpkh_script(1)));
+
+        assert_eq!(tx.inputs[1].sighash_type(), Some(0x83));
+        assert_eq!(
+            policy::check_standardness(&tx, policy::DEFAULT_DUST_RELAY_FEE),
+            Err(vec![
+                policy::StandardnessViolation::SighashSingleWithoutOutput { input_index: 1 }
+            ])
+        );
+
+        tx.outputs
+            .push(TransactionOutput::new(50_000, p2wpkh_script(2)));
+        assert_eq!(
+            policy::check_standardness(&tx, policy::DEFAULT_DUST_RELAY_FEE),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_with_version() {
+        let (tx, _) = BitcoinTransaction::from_bytes(&hex::decode(BLOCK_170_TX).unwrap()).unwrap();
+        assert_eq!(tx.version, 1);
+        assert!(!tx.enforces_bip68());
+
+        let bumped = tx.with_version(2);
+        assert_eq!(bumped.version, 2);
+        assert!(bumped.enforces_bip68());
+        assert_eq!(bumped.inputs, tx.inputs);
+        assert_eq!(bumped.outputs, tx.outputs);
+        assert_eq!(bumped.lock_time, tx.lock_time);
+        assert_ne!(bumped.txid(), tx.txid());
+        assert_eq!(tx.version, 1);
+    }
+
+    #[test]
+    fn test_from_bytes_detect() {
+        let legacy = hex::decode(BLOCK_170_TX).unwrap();
+        let (tx, had_witness) = BitcoinTransaction::from_bytes_detect(&legacy).unwrap();
+        assert!(!had_witness);
+        assert_eq!(tx.to_bytes(), legacy);
+
+        let segwit = segwit_tx_with_witness_items(2);
+        let (tx, had_witness) = BitcoinTransaction::from_bytes_detect(&segwit).unwrap();
+        assert!(had_witness);
+        assert_eq!(tx.to_bytes(), segwit);
+
+        assert_eq!(
+            BitcoinTransaction::from_bytes_detect(&segwit[..10]),
+            Err(BitcoinError::InsufficientBytes)
+        );
+    }
+
+    /// A scriptSig claiming `len` bytes, inside an otherwise valid one-input
+    /// transaction prefix.
+    fn tx_with_script_sig_length(len: &[u8]) -> Vec<u8> {
+        let mut bytes = vec![0x01, 0x00, 0x00, 0x00, 0x01];
+        bytes.extend_from_slice(&[0x11; 32]);
+        bytes.extend_from_slice(&[0x00; 4]);
+        bytes.extend_from_slice(len);
+        bytes.extend_from_slice(&[0xFF; 8]);
+        bytes
+    }
+
+    #[test]
+    fn test_parser_rejects_huge_lengths() {
+        for len in [
+            vec![0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF],
+            vec![0xFE, 0xFF, 0xFF, 0xFF, 0xFF],
+            vec![0xFF, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00],
+        ] {
+            let bytes = tx_with_script_sig_length(&len);
+            assert_eq!(
+                BitcoinTransaction::from_bytes(&bytes),
+                Err(BitcoinError::InsufficientBytes)
+            );
+            assert_eq!(
+                BitcoinTransaction::from_bytes_inputs_only(&bytes),
+                Err(BitcoinError::InsufficientBytes)
+            );
+        }
+    }
+
+    // On 32-bit targets a length just under `usize::MAX` is where unchecked
+    // offset arithmetic would wrap.
+    #[cfg(target_pointer_width = "32")]
+    #[test]
+    fn test_parser_offsets_do_not_wrap_on_32_bit() {
+        let bytes = tx_with_script_sig_length(&[0xFE, 0xFC, 0xFF, 0xFF, 0xFF]);
+        assert!(BitcoinTransaction::from_bytes(&bytes).is_err());
+        let mut witness = vec![0x01, 0xFE, 0xFC, 0xFF, 0xFF, 0xFF];
+        witness.extend_from_slice(&[0x00; 8]);
+        assert!(Witness::from_bytes(&witness).is_err());
+    }
+
+    #[test]
+    fn test_consensus_codec_traits() {
+        use encode::{Decodable, Encodable};
+
+        fn roundtrip<T: Encodable + Decodable + PartialEq + std::fmt::Debug>(value: T) {
+            let bytes = value.consensus_encode();
+            assert_eq!(T::consensus_decode(&bytes), Ok((value, bytes.len())));
+        }
+
+        let (tx, _) = BitcoinTransaction::from_bytes(&hex::decode(BLOCK_170_TX).unwrap()).unwrap();
+        roundtrip(CompactSize::new(70_000));
+        roundtrip(tx.inputs[0].clone());
+        roundtrip(tx.outputs[1].clone());
+        roundtrip(Witness::new(vec![vec![0x01], vec![0x02; 33]]));
+        roundtrip(dummy_header(3));
+        roundtrip(tx);
+    }
+
+    #[test]
+    fn test_parse_p2pkh_spend() {
+        let mut signature = der_signature(&[0x3A; 32], &[0x1C; 32]);
+        signature.push(0x01);
+        let pubkey =
+            hex::decode("0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798")
+                .unwrap();
+        let mut bytes = vec![signature.len() as u8];
+        bytes.extend_from_slice(&signature);
+        bytes.push(pubkey.len() as u8);
+        bytes.extend_from_slice(&pubkey);
+        let script_sig = Script::new(bytes.clone());
+        assert_eq!(script_sig.parse_p2pkh_spend(), Some((signature, pubkey)));
+
+        bytes.push(0x51);
+        assert_eq!(Script::new(bytes).parse_p2pkh_spend(), None);
+
+        // Block 170's input spends a P2PK output: a signature and nothing else.
+        let (tx, _) = BitcoinTransaction::from_bytes(&hex::decode(BLOCK_170_TX).unwrap()).unwrap();
+        assert_eq!(tx.inputs[0].script_sig.parse_p2pkh_spend(), None);
+    }
+
+    #[test]
+    fn test_all_script_data() {
+        let pubkey = vec![0x02; 33];
+        let mut legacy_sig = vec![0x47];
+        legacy_sig.extend_from_slice(&[0x30; 71]);
+        legacy_sig.push(0x21);
+        legacy_sig.extend_from_slice(&pubkey);
+        let legacy = TransactionInput::new(
+            OutPoint::new(dummy_txid(1), 0),
+            Script::new(legacy_sig),
+            SEQUENCE_FINAL,
+        );
+        let mut segwit =
+            TransactionInput::new(OutPoint::new(dummy_txid(2), 0), Script::new(vec![]), 0);
+        segwit.witness = Witness::new(vec![vec![0x30; 72], vec![], pubkey.clone()]);
+        let tx = BitcoinTransaction::new(2, vec![legacy, segwit], 0);
+
+        let data = tx.all_script_data();
+        assert_eq!(data.len(), 4);
+        assert_eq!(data[0], &[0x30; 71][..]);
+        assert_eq!(data.iter().filter(|d| **d == &pubkey[..]).count(), 2);
+    }
+
+    #[test]
+    fn test_amount_try_from_btc() {
+        assert_eq!(Amount::try_from_btc(0.1), Ok(Amount::from_sat(10_000_000)));
+        assert_eq!(Amount::try_from_btc(0.0), Ok(Amount::ZERO));
+        assert_eq!(Amount::try_from_btc(21_000_000.0), Ok(Amount::MAX_MONEY));
+        // 1.4 and 1.6 satoshis.
+        assert_eq!(Amount::try_from_btc(0.000_000_014), Ok(Amount::from_sat(1)));
+        assert_eq!(Amount::try_from_btc(0.000_000_016), Ok(Amount::from_sat(2)));
+
+        // Negative amounts are rejected even when they round to zero.
+        for invalid in [21_000_001.0, -0.1, -0.000_000_001, f64::NAN, f64::INFINITY] {
+            assert_eq!(
+                Amount::try_from_btc(invalid),
+                Err(BitcoinError::InvalidFormat)
+            );
+        }
+    }
+
+    #[test]
+    fn test_effective_value() {
+        // A typical P2WPKH input is 68 vbytes.
+        let rate = FeeRate::from_sat_per_vb(10);
+        assert_eq!(rate.fee_for_vsize(68), Amount::from_sat(680));
+
+        assert_eq!(
+            amount::effective_value(Amount::from_sat(100_000), 68, rate),
+            99_320
+        );
+        assert_eq!(amount::effective_value(Amount::from_sat(680), 68, rate), 0);
+        assert_eq!(
+            amount::effective_value(Amount::from_sat(300), 68, rate),
+            -380
+        );
+
+        // Overflowing products saturate instead of panicking or wrapping.
+        let huge = FeeRate::from_sat_per_vb(u64::MAX);
+        assert_eq!(huge, FeeRate(u64::MAX));
+        assert_eq!(huge.fee_for(4), Amount::from_sat(u64::MAX));
+        assert_eq!(huge.fee_for_vsize(2), Amount::from_sat(u64::MAX));
+        assert_eq!(
+            amount::effective_value(Amount::from_sat(100_000), 68, huge),
+            100_000 - i64::MAX
+        );
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let (tx, _) = BitcoinTransaction::from_bytes(&hex::decode(BLOCK_170_TX).unwrap()).unwrap();
+        let dot = tx.to_dot();
+        assert!(dot.starts_with("digraph transaction {"));
+        assert!(dot.ends_with("}\n"));
+
+        let nodes = dot.lines().filter(|line| line.contains("[")).count();
+        assert_eq!(nodes, 1 + tx.inputs.len() + tx.outputs.len());
+        assert_eq!(
+            dot.matches(" -> ").count(),
+            tx.inputs.len() + tx.outputs.len()
+        );
+        assert!(dot.contains(&format!("label=\"{}\"", BLOCK_170_TXID)));
+        assert!(dot.contains("1000000000 sat\\nP2pk"));
+    }
+
+    #[test]
+    fn test_is_canonically_encoded() {
+        let canonical = hex::decode(BLOCK_170_TX).unwrap();
+        let (tx, _) = BitcoinTransaction::from_bytes(&canonical).unwrap();
+        assert!(tx.is_canonically_encoded(&canonical));
+
+        // The single-byte input count 0x01 written as the three-byte 0xfd0100.
+        let mut padded = canonical[..4].to_vec();
+        padded.extend_from_slice(&[0xFD, 0x01, 0x00]);
+        padded.extend_from_slice(&canonical[5..]);
+        let (parsed, consumed) = BitcoinTransaction::from_bytes(&padded).unwrap();
+        assert_eq!(consumed, padded.len());
+        assert_eq!(parsed, tx);
+        assert!(!parsed.is_canonically_encoded(&padded));
+    }
+
+    #[test]
+    fn test_compute_txids() {
+        let (base, _) =
+            BitcoinTransaction::from_bytes(&hex::decode(BLOCK_170_TX).unwrap()).unwrap();
+        let txs: Vec<BitcoinTransaction> = (0..37).map(|i| base.with_version(i)).collect();
+
+        let sequential: Vec<Txid> = txs.iter().map(BitcoinTransaction::txid).collect();
+        assert_eq!(compute_txids(&txs), sequential);
+        assert_eq!(compute_txids(&txs[..1]), sequential[..1]);
+        assert!(compute_txids(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_psbt_witness_utxo_roundtrip() {
+        let mut psbt = psbt::Psbt::new(bip143_p2wpkh_tx());
+        assert_eq!(psbt.inputs.len(), 2);
+        assert_eq!(psbt.outputs.len(), 2);
+        psbt.inputs[1].witness_utxo =
+            Some(TransactionOutput::new(600_000_000, p2wpkh_script(0x1d)));
+
+        let bytes = psbt.to_bytes();
+        assert_eq!(&bytes[..5], b"psbt\xff");
+        assert_eq!(
+            psbt::Psbt::from_bytes(&bytes),
+            Ok((psbt.clone(), bytes.len()))
+        );
+
+        let (prevout_tx, _) =
+            BitcoinTransaction::from_bytes(&hex::decode(BLOCK_170_TX).unwrap()).unwrap();
+        psbt.inputs[0].non_witness_utxo = Some(prevout_tx);
+        psbt.inputs[0].unknown.push((vec![0xFC, 0x01], vec![0xAA]));
+        let bytes = psbt.to_bytes();
+        assert_eq!(psbt::Psbt::from_bytes(&bytes), Ok((psbt, bytes.len())));
+
+        assert_eq!(
+            psbt::Psbt::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(BitcoinError::InsufficientBytes)
+        );
+        let mut bad_magic = bytes.clone();
+        bad_magic[4] = 0x00;
+        assert_eq!(
+            psbt::Psbt::from_bytes(&bad_magic),
+            Err(BitcoinError::InvalidFormat)
+        );
+    }
+
+    #[test]
+    fn test_likely_change_index() {
+        let mut input =
+            TransactionInput::new(OutPoint::new(dummy_txid(1), 0), Script::new(vec![]), 0);
+        input.witness = Witness::new(vec![der_signature(&[0x01], &[0x01]), vec![0x02; 33]]);
+        let mut tx = BitcoinTransaction::new(2, vec![input], 0);
+        tx.outputs = vec![
+            TransactionOutput::new(5_000_000, p2pkh_script(0x01)),
+            TransactionOutput::new(1_234_567, p2wpkh_script(0x02)),
+        ];
+        assert_eq!(tx.likely_change_index(), Some(1));
+
+        tx.outputs[1].value = 5_000_000;
+        assert_eq!(tx.likely_change_index(), None);
+    }
+
+    /// Parses every prefix of `bytes`, asserting each one short of the full
+    /// transaction is `InsufficientBytes` and the full input parses with all
+    /// of it consumed.
+    fn parse_progressively(bytes: &[u8]) -> BitcoinTransaction {
+        for len in 0..bytes.len() {
+            assert_eq!(
+                BitcoinTransaction::from_bytes(&bytes[..len]),
+                Err(BitcoinError::InsufficientBytes),
+                "prefix of {len} bytes"
+            );
+        }
+        let (tx, consumed) = BitcoinTransaction::from_bytes(bytes).unwrap();
+        assert_eq!(consumed, bytes.len());
+        tx
+    }
+
+    #[test]
+    fn test_parse_progressively() {
+        let legacy = hex::decode(BLOCK_170_TX).unwrap();
+        assert_eq!(parse_progressively(&legacy).to_bytes(), legacy);
+
+        let segwit = hex::decode(BIP143_P2WPKH_TX).unwrap();
+        assert_eq!(parse_progressively(&segwit).to_bytes(), segwit);
+    }
+
+    #[test]
+    fn test_is_provably_unspendable() {
+        assert!(Script::new(vec![0x6A, 0x01, 0xFF]).is_provably_unspendable());
+        assert!(Script::new(vec![0x51; 10_001]).is_provably_unspendable());
+        assert!(!p2wpkh_script(0x01).is_provably_unspendable());
+        assert!(!Script::new(vec![0x51; 10_000]).is_provably_unspendable());
+    }
+
+    #[test]
+    fn test_hex_error_conversion() {
+        let err = hex::decode("0g").unwrap_err();
+        assert_eq!(BitcoinError::from(err), BitcoinError::InvalidFormat);
+        assert_eq!(
+            Txid::from_display_hex("abc"),
+            Err(BitcoinError::InvalidFormat)
+        );
+    }
+
+    #[test]
+    fn test_serialized_len() {
+        let mut big_script = BitcoinTransaction::new(1, vec![], 0);
+        big_script
+            .outputs
+            .push(TransactionOutput::new(0, Script::new(vec![0x51; 300])));
+        let txs = [
+            BitcoinTransaction::from_bytes(&hex::decode(BLOCK_170_TX).unwrap())
+                .unwrap()
+                .0,
+            BitcoinTransaction::from_bytes(&hex::decode(BIP143_P2WPKH_TX).unwrap())
+                .unwrap()
+                .0,
+            BitcoinTransaction::from_bytes(&segwit_tx_with_witness_items(3))
+                .unwrap()
+                .0,
+            big_script,
+        ];
+        for tx in &txs {
+            assert_eq!(tx.serialized_len(), tx.to_bytes().len());
+        }
+        assert_eq!(CompactSize::new(0x1_0000).serialized_len(), 5);
+    }
+
+    #[test]
+    fn test_compact_size_from_bytes_bounded() {
+        let bytes = [0xFD, 0xE8, 0x03];
+        assert_eq!(
+            CompactSize::from_bytes_bounded(&bytes, 1_000),
+            Ok((CompactSize::new(1_000), 3))
+        );
+        assert_eq!(
+            CompactSize::from_bytes_bounded(&bytes, 999),
+            Err(BitcoinError::InvalidFormat)
+        );
+        assert_eq!(
+            CompactSize::from_bytes_bounded(&bytes[..2], 999),
+            Err(BitcoinError::InsufficientBytes)
+        );
+    }
+
+    #[test]
+    fn test_is_signed() {
+        let (signed, _) =
+            BitcoinTransaction::from_bytes(&hex::decode(BLOCK_170_TX).unwrap()).unwrap();
+        assert!(signed.is_signed());
+
+        let unsigned = bip143_p2wpkh_tx();
+        assert!(!unsigned.is_signed());
+    }
+
+    #[test]
+    fn test_script_num() {
+        let cases: [(i64, &[u8]); 7] = [
+            (0, &[]),
+            (-1, &[0x81]),
+            (127, &[0x7F]),
+            (128, &[0x80, 0x00]),
+            (-128, &[0x80, 0x80]),
+            (-255, &[0xFF, 0x80]),
+            (500_000, &[0x20, 0xA1, 0x07]),
+        ];
+        for (n, bytes) in cases {
+            assert_eq!(script::script_num_encode(n), bytes);
+            assert_eq!(script::script_num_decode(bytes), Ok(n));
+        }
+
+        for bad in [&[0x00][..], &[0x80], &[0x7F, 0x00], &[0x01; 6]] {
+            assert_eq!(
+                script::script_num_decode(bad),
+                Err(BitcoinError::InvalidFormat)
+            );
+        }
+        assert_eq!(
+            script::script_num_decode(&[0xFF, 0xFF, 0xFF, 0xFF, 0x00]),
+            Ok(0xFFFF_FFFF)
+        );
+
+        // Arithmetic operands stop at 4 bytes; lock time operands allow 5.
+        let four = [0xFF, 0xFF, 0xFF, 0x7F];
+        let five = [0xFF, 0xFF, 0xFF, 0xFF, 0x00];
+        assert_eq!(
+            script::script_num_decode_with_max(&four, 4),
+            Ok(0x7FFF_FFFF)
+        );
+        assert_eq!(
+            script::script_num_decode_with_max(&five, 4),
+            Err(BitcoinError::InvalidFormat)
+        );
+        assert_eq!(
+            script::script_num_decode_with_max(&five, 5),
+            Ok(0xFFFF_FFFF)
+        );
+        assert_eq!(
+            script::script_num_decode_with_max(&[0x01; 6], 5),
+            Err(BitcoinError::InvalidFormat)
+        );
+    }
+
+    #[test]
+    fn test_simple_transfer() {
+        let prevout = OutPoint::new(dummy_txid(1), 0);
+        let tx = BitcoinTransaction::simple_transfer(
+            prevout.clone(),
+            Script::new(vec![0x51]),
+            p2wpkh_script(0x02),
+            Amount::from_sat(90_000),
+            Amount::from_sat(1_000),
+            Amount::from_sat(91_000),
+        )
+        .unwrap();
+        assert_eq!(tx.version, 2);
+        assert_eq!(tx.inputs[0].previous_output, prevout);
+        assert_eq!(tx.inputs[0].script_sig, Script::new(vec![0x51]));
+        assert_eq!(
+            tx.outputs,
+            vec![TransactionOutput::new(90_000, p2wpkh_script(0x02))]
+        );
+
+        assert_eq!(
+            BitcoinTransaction::simple_transfer(
+                prevout.clone(),
+                Script::new(vec![]),
+                p2wpkh_script(0x02),
+                Amount::from_sat(90_000),
+                Amount::from_sat(1_000),
+                Amount::from_sat(90_999),
+            ),
+            Err(BitcoinError::InvalidFormat)
+        );
+        // With no change output the surplus satoshi is paid as extra fee.
+        let overfunded = BitcoinTransaction::simple_transfer(
+            prevout,
+            Script::new(vec![]),
+            p2wpkh_script(0x02),
+            Amount::from_sat(90_000),
+            Amount::from_sat(1_000),
+            Amount::from_sat(91_001),
+        )
+        .unwrap();
+        assert_eq!(
+            overfunded.outputs,
+            vec![TransactionOutput::new(90_000, p2wpkh_script(0x02))]
+        );
+        let paid: u64 = overfunded.outputs.iter().map(|o| o.value).sum();
+        assert_eq!(91_001 - paid, 1_001);
+    }
+
+    #[test]
+    fn test_spends() {
+        let tx = bip143_p2wpkh_tx();
+        let spent = tx.inputs[1].previous_output.clone();
+        assert!(tx.spends(&spent));
+        assert!(!tx.spends(&OutPoint::new(spent.txid.0, spent.vout + 1)));
+    }
+
+    #[test]
+    fn test_witness_builder() {
+        let mut signature = der_signature(&[0x01; 32], &[0x02; 32]);
+        signature.push(0x01);
+        let witness = WitnessBuilder::new()
+            .push_signature(&signature)
+            .and_then(|b| b.push_key(&[0x02; 33]))
+            .unwrap()
+            .build();
+        assert_eq!(witness.len(), 2);
+        assert_eq!(witness.get(0), Some(&signature[..]));
+
+        assert_eq!(
+            WitnessBuilder::new().push_key(&[0x02; 32]).unwrap_err(),
+            BitcoinError::InvalidFormat
+        );
+        assert!(
+            WitnessBuilder::new()
+                .push_script(&Script::new(vec![0x51; 10_001]))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_from_consensus_bytes() {
+        let bytes = hex::decode(BLOCK_170_TX).unwrap();
+        let tx = BitcoinTransaction::from_consensus_bytes(&bytes).unwrap();
+        assert_eq!(tx.to_bytes(), bytes);
+
+        let mut wide_count = bytes[..4].to_vec();
+        wide_count.extend_from_slice(&[0xFD, 0x01, 0x00]);
+        wide_count.extend_from_slice(&bytes[5..]);
+        assert!(BitcoinTransaction::from_bytes(&wide_count).is_ok());
+        assert_eq!(
+            BitcoinTransaction::from_consensus_bytes(&wide_count),
+            Err(BitcoinError::InvalidFormat)
+        );
+
+        let mut trailing = bytes.clone();
+        trailing.push(0x00);
+        assert_eq!(
+            BitcoinTransaction::from_consensus_bytes(&trailing),
+            Err(BitcoinError::InvalidFormat)
+        );
+
+        let mut double_spend = tx.clone();
+        double_spend.inputs.push(tx.inputs[0].clone());
+        assert_eq!(
+            BitcoinTransaction::from_consensus_bytes(&double_spend.to_bytes()),
+            Err(BitcoinError::InvalidFormat)
+        );
+
+        // OP_PUSHDATA1 for a single byte that a direct push could carry.
+        let mut non_minimal = tx.clone();
+        non_minimal.inputs[0].script_sig = Script::new(vec![0x4C, 0x01, 0xAA]);
+        assert_eq!(
+            BitcoinTransaction::from_consensus_bytes(&non_minimal.to_bytes()),
+            Err(BitcoinError::InvalidFormat)
+        );
+    }
+
+    #[test]
+    fn test_has_minimal_pushes() {
+        for minimal in [
+            &[][..],
+            &[0x00],
+            &[0x51],
+            &[0x4F],
+            &[0x01, 0x11],
+            &[0x02, 0xAA, 0xBB, 0x76],
+        ] {
+            assert!(Script::new(minimal.to_vec()).has_minimal_pushes());
+        }
+        let mut pushdata1 = vec![0x4C, 0x4C];
+        pushdata1.extend_from_slice(&[0xAA; 0x4C]);
+        assert!(Script::new(pushdata1).has_minimal_pushes());
+
+        for non_minimal in [
+            &[0x4C, 0x00][..],
+            &[0x01, 0x05],
+            &[0x01, 0x81],
+            &[0x4C, 0x01, 0xAA],
+            &[0x4D, 0x01, 0x00, 0xAA],
+            &[0x02, 0xAA],
+        ] {
+            assert!(!Script::new(non_minimal.to_vec()).has_minimal_pushes());
+        }
+    }
+
+    #[test]
+    fn test_input_weight() {
+        let legacy = BitcoinTransaction::from_bytes(&hex::decode(BLOCK_170_TX).unwrap())
+            .unwrap()
+            .0;
+        let segwit = BitcoinTransaction::from_bytes(&segwit_tx_with_witness_items(3))
+            .unwrap()
+            .0;
+        for tx in [legacy, segwit] {
+            let outputs: usize = tx.outputs.iter().map(|o| o.serialized_len()).sum();
+            let mut overhead = (4 + 1 + 1 + outputs + 4) * 4;
+            if tx.has_witness() {
+                overhead += 2;
+            }
+            let inputs: usize = tx.inputs.iter().map(|i| i.input_weight()).sum();
+            assert_eq!(inputs, tx.weight() - overhead);
+        }
+    }
+
+    #[test]
+    fn test_op_return_pushes() {
+        let script = Script::new(vec![0x6A, 0x02, 0xAA, 0xBB, 0x01, 0xCC]);
+        assert_eq!(
+            script.op_return_pushes(),
+            Some(vec![&[0xAA, 0xBB][..], &[0xCC][..]])
+        );
+        assert_eq!(script.op_return_data(), Some(&[0xAA, 0xBB][..]));
+        assert_eq!(Script::new(vec![0x6A]).op_return_pushes(), Some(vec![]));
+        assert_eq!(
+            Script::new(vec![0x6A, 0x01, 0xCC, 0x76]).op_return_pushes(),
+            None
+        );
+        assert_eq!(p2wpkh_script(0x01).op_return_pushes(), None);
+    }
+
+    #[test]
+    fn test_iter_outputs_with_outpoints() {
+        let tx = BitcoinTransaction::from_bytes(&hex::decode(BLOCK_170_TX).unwrap())
+            .unwrap()
+            .0;
+        let pairs: Vec<_> = tx.iter_outputs_with_outpoints().collect();
+        assert_eq!(pairs.len(), 2);
+        for (i, (outpoint, output)) in pairs.into_iter().enumerate() {
+            assert_eq!(outpoint.txid.to_display_string(), BLOCK_170_TXID);
+            assert_eq!(outpoint.vout, i as u32);
+            assert_eq!(output, &tx.outputs[i]);
+        }
+    }
+
+    #[test]
+    fn test_reject_roundtrip() {
+        let msg = p2p::RejectMessage {
+            message: "tx".to_string(),
+            ccode: 0x42,
+            reason: "min relay fee not met".to_string(),
+            data: Some(dummy_txid(7)),
+        };
+        let bytes = msg.to_bytes();
+        assert_eq!(bytes.len(), 3 + 1 + 22 + 32);
+        assert_eq!(
+            p2p::RejectMessage::from_bytes(&bytes),
+            Ok((msg.clone(), bytes.len()))
+        );
+        assert_eq!(
+            p2p::RejectMessage::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(BitcoinError::InsufficientBytes)
+        );
+
+        let without_data = p2p::RejectMessage { data: None, ..msg };
+        let bytes = without_data.to_bytes();
+        assert_eq!(
+            p2p::RejectMessage::from_bytes(&bytes),
+            Ok((without_data, bytes.len()))
+        );
+    }
+
+    #[test]
+    fn test_fits_fee_budget() {
+        let tx = BitcoinTransaction::from_bytes(&hex::decode(BLOCK_170_TX).unwrap())
+            .unwrap()
+            .0;
+        let rate = FeeRate::from_sat_per_vb(3);
+        let fee = tx.vsize() as u64 * 3;
+        assert!(tx.fits_fee_budget(Amount::from_sat(fee), rate));
+        assert!(!tx.fits_fee_budget(Amount::from_sat(fee - 1), rate));
+    }
+
+    #[test]
+    fn test_null_outpoint() {
+        assert_eq!(Txid::all_zeros().to_raw_bytes(), [0; 32]);
+        let null = OutPoint::null();
+        assert!(null.is_null());
+        assert_eq!(null.to_bytes(), [&[0u8; 32][..], &[0xFF; 4]].concat());
+        assert!(!OutPoint::new([0; 32], 0).is_null());
+        assert!(!OutPoint::new(dummy_txid(1), u32::MAX).is_null());
+    }
+
+    #[test]
+    fn test_sigop_adjusted_vsize() {
+        let tx = BitcoinTransaction::from_bytes(&hex::decode(BLOCK_170_TX).unwrap())
+            .unwrap()
+            .0;
+        assert_eq!(tx.sigop_adjusted_vsize(2), tx.vsize());
+        assert_eq!(tx.sigop_adjusted_vsize(80), 80 * 20);
+        assert!(80 * 20 > tx.vsize());
+    }
+
+    /// A bare `m`-of-`n` multisig script with keys `[1; 33]`, `[2; 33]`, ...
+    fn multisig_script(m: u8, n: u8) -> Script {
+        let mut bytes = vec![0x50 + m];
+        for key in 1..=n {
+            bytes.push(0x21);
+            bytes.extend_from_slice(&[key; 33]);
+        }
+        bytes.extend_from_slice(&[0x50 + n, 0xAE]);
+        Script::new(bytes)
+    }
+
+    #[test]
+    fn test_count_sigops() {
+        let mut tx = BitcoinTransaction::new(2, vec![], 0);
+        tx.outputs = vec![
+            TransactionOutput::new(1_000, multisig_script(2, 3)),
+            TransactionOutput::new(1_000, p2pkh_script(0x01)),
+            TransactionOutput::new(1_000, p2wpkh_script(0x02)),
+            TransactionOutput::new(1_000, Script::new(vec![0x00, 0xAE])),
+        ];
+        assert_eq!(tx.outputs[0].script_pubkey.sigop_count(), 3);
+        assert_eq!(tx.count_sigops(), 3 + 1 + 20);
+    }
+
+    #[test]
+    fn test_parse_bare_multisig() {
+        let (m, pubkeys, n) = multisig_script(2, 3).parse_bare_multisig().unwrap();
+        assert_eq!((m, n), (2, 3));
+        assert_eq!(pubkeys, vec![vec![1; 33], vec![2; 33], vec![3; 33]]);
+
+        let mut bytes = multisig_script(2, 3).bytes;
+        bytes[0] = 0x54;
+        assert_eq!(Script::new(bytes).parse_bare_multisig(), None);
+        let mut bytes = multisig_script(2, 3).bytes;
+        let n_index = bytes.len() - 2;
+        bytes[n_index] = 0x52;
+        assert_eq!(Script::new(bytes).parse_bare_multisig(), None);
+        assert_eq!(p2pkh_script(0x01).parse_bare_multisig(), None);
+    }
+
+    #[test]
+    fn test_fee_rate_buckets() {
+        let tx = BitcoinTransaction::from_bytes(&hex::decode(BLOCK_170_TX).unwrap())
+            .unwrap()
+            .0;
+        let vsize = tx.vsize() as u64;
+        let fees: Vec<_> = [0, vsize, 5 * vsize - 1, 5 * vsize, 20 * vsize]
+            .into_iter()
+            .map(|fee| (&tx, Amount::from_sat(fee)))
+            .collect();
+        assert_eq!(
+            policy::fee_rate_buckets(&fees, &[1.0, 5.0, 10.0]),
+            vec![1, 2, 1, 1]
+        );
+        assert_eq!(policy::fee_rate_buckets(&fees, &[]), vec![5]);
+    }
+
+    #[test]
+    fn test_script_semantic_eq() {
+        let minimal = Script::new(vec![0x02, 0xAA, 0xBB, 0x87]);
+        let pushdata1 = Script::new(vec![0x4C, 0x02, 0xAA, 0xBB, 0x87]);
+        let pushdata2 = Script::new(vec![0x4D, 0x02, 0x00, 0xAA, 0xBB, 0x87]);
+        assert_ne!(minimal, pushdata1);
+        assert!(minimal.semantic_eq(&pushdata1));
+        assert!(pushdata2.semantic_eq(&minimal));
+        assert!(!minimal.semantic_eq(&Script::new(vec![0x02, 0xAA, 0xBC, 0x87])));
+        assert!(!Script::new(vec![0x4C]).semantic_eq(&Script::new(vec![0x4D])));
+    }
+
+    #[test]
+    fn test_split_block() {
+        let (_, txs) = block_file_bytes();
+        let block = block::Block::new(dummy_header(3), txs.clone());
+        let bytes = block.to_bytes();
+
+        let (header, count, tx_region) = block::split_block(&bytes).unwrap();
+        assert_eq!(header, &dummy_header(3).to_bytes()[..]);
+        assert_eq!(count, txs.len());
+        let tx_bytes: Vec<u8> = txs.iter().flat_map(|tx| tx.to_bytes()).collect();
+        assert_eq!(tx_region, &tx_bytes[..]);
+        assert_eq!(
+            [
+                header,
+                &CompactSize::new(count as u64).to_bytes(),
+                tx_region
+            ]
+            .concat(),
+            bytes
+        );
+
+        assert_eq!(
+            block::split_block(&bytes[..80]),
+            Err(BitcoinError::InsufficientBytes)
+        );
+    }
+
+    #[test]
+    fn test_merkle_root() {
+        let coinbase = Txid::from_display_hex(
+            "b1fea52486ce0c62bb442b530a3f0132b826c74e473d1f2c220bfa78111c5082",
+        )
+        .unwrap();
+        let spend = Txid::from_display_hex(BLOCK_170_TXID).unwrap();
+        let root = Txid::from_raw_bytes(block::merkle_root(&[coinbase, spend]));
+        assert_eq!(
+            root.to_display_string(),
+            "7dac2c5666815c17a3b36427de37bb9d2e2c5ccec3f8633eb91a4205cb4c10ff"
+        );
+    }
+
+    #[test]
+    fn test_verify_transactions() {
+        let (_, mut txs) = block_file_bytes();
+        txs.push(bip143_p2wpkh_tx());
+        assert_eq!(txs.len(), 3);
+        let ids: Vec<[u8; 32]> = txs.iter().map(|tx| tx.txid().to_raw_bytes()).collect();
+        let left = hashes::double_sha256(&[ids[0], ids[1]].concat());
+        let right = hashes::double_sha256(&[ids[2], ids[2]].concat());
+        let root = hashes::double_sha256(&[left, right].concat());
+
+        assert!(block::verify_transactions(&txs, root));
+        txs.swap(0, 1);
+        assert!(!block::verify_transactions(&txs, root));
+    }
+
+    #[test]
+    fn test_script_hex() {
+        let hex_str = "76a91462e907b15cbf27d5425399ebf6f0fb50ebb88f1888ac";
+        let script = Script::from_hex(hex_str).unwrap();
+        assert_eq!(script.script_type(), ScriptType::P2pkh);
+        assert_eq!(script.len(), 25);
+        assert_eq!(script.to_hex(), hex_str);
+        assert_eq!(hex::encode(script.to_bytes()), format!("19{hex_str}"));
+        assert_eq!(Script::from_hex("76a9x"), Err(BitcoinError::InvalidFormat));
+    }
+
+    #[test]
+    fn test_taproot_spend_kind() {
+        let key_path = Witness::new(vec![vec![0x01; 64]]);
+        assert_eq!(key_path.taproot_annex(), None);
+        assert_eq!(
+            key_path.taproot_spend_kind(),
+            Some(TaprootSpendKind::KeyPath)
+        );
+
+        let with_annex = Witness::new(vec![vec![0x01; 64], vec![0x50, 0xAA]]);
+        assert_eq!(with_annex.taproot_annex(), Some(&[0x50, 0xAA][..]));
+        assert_eq!(
+            with_annex.taproot_spend_kind(),
+            Some(TaprootSpendKind::KeyPath)
+        );
+
+        let script_path = Witness::new(vec![vec![0x01; 64], vec![0x51], vec![0xC0; 33 + 32]]);
+        assert_eq!(
+            script_path.taproot_spend_kind(),
+            Some(TaprootSpendKind::ScriptPath)
+        );
+
+        let bad_control = Witness::new(vec![vec![0x51], vec![0xC0; 34]]);
+        assert_eq!(bad_control.taproot_spend_kind(), None);
+        assert_eq!(Witness::default().taproot_spend_kind(), None);
+    }
+
+    #[test]
+    fn test_earliest_spendable_height_locked() {
+        let input = TransactionInput::new(OutPoint::new(dummy_txid(1), 0), Script::new(vec![]), 0)
+            .with_sequence(SEQUENCE_FINAL - 1);
+        let tx = BitcoinTransaction::new(2, vec![input], 800_000);
+        assert_eq!(tx.lock_time_kind(), locktime::LockTimeKind::Height(800_000));
+
+        let waiting = tx.earliest_spendable(799_999, 0);
+        assert_eq!(
+            waiting.pending_lock_time,
+            Some(locktime::LockTimeKind::Height(800_000))
+        );
+        assert!(!waiting.is_spendable_now());
+        assert!(tx.earliest_spendable(800_000, 0).is_spendable_now());
+
+        let mut all_final = tx.clone();
+        all_final.inputs[0].sequence = SEQUENCE_FINAL;
+        assert_eq!(all_final.lock_time_kind(), locktime::LockTimeKind::Disabled);
+        assert!(all_final.earliest_spendable(0, 0).is_spendable_now());
+    }
+
+    #[test]
+    fn test_earliest_spendable_time_locked() {
+        let time_lock =
+            TransactionInput::new(OutPoint::new(dummy_txid(1), 0), Script::new(vec![]), 0)
+                .with_sequence(locktime::SEQUENCE_LOCKTIME_TYPE_FLAG | 3);
+        let block_lock =
+            TransactionInput::new(OutPoint::new(dummy_txid(2), 0), Script::new(vec![]), 0)
+                .with_sequence(10);
+        assert_eq!(
+            time_lock.relative_locktime(),
+            Some(locktime::RelativeLockTime::Seconds(1_536))
+        );
+        let tx = BitcoinTransaction::new(2, vec![time_lock, block_lock], 1_700_000_000);
+        assert_eq!(
+            tx.lock_time_kind(),
+            locktime::LockTimeKind::Time(1_700_000_000)
+        );
+
+        let spendability = tx.earliest_spendable(900_000, 1_700_000_001);
+        assert_eq!(spendability.pending_lock_time, None);
+        assert_eq!(spendability.relative_blocks, 10);
+        assert_eq!(spendability.relative_seconds, 1_536);
+        assert!(!spendability.is_spendable_now());
+
+        let v1 = tx
+            .with_version(1)
+            .earliest_spendable(900_000, 1_700_000_001);
+        assert!(v1.is_spendable_now());
+        assert!(
+            tx.earliest_spendable(900_000, 1_700_000_000)
+                .pending_lock_time
+                .is_some()
+        );
+    }
+
+    #[test]
+    fn test_outpoint_rpc_string() {
+        let rpc = format!("{BLOCK_170_TXID}:1");
+        let outpoint = OutPoint::from_rpc_string(&rpc).unwrap();
+        assert_eq!(outpoint.vout, 1);
+        assert_eq!(outpoint.txid.to_display_string(), BLOCK_170_TXID);
+        assert_eq!(outpoint.to_rpc_string(), rpc);
+        assert_eq!(
+            OutPoint::from_rpc_string(&outpoint.to_rpc_string()),
+            Ok(outpoint)
+        );
+
+        for bad in [
+            BLOCK_170_TXID.to_string(),
+            format!("{}:0", &BLOCK_170_TXID[1..]),
+            format!("{BLOCK_170_TXID}:-1"),
+        ] {
+            assert_eq!(
+                OutPoint::from_rpc_string(&bad),
+                Err(BitcoinError::InvalidFormat)
+            );
+        }
+    }
+
+    #[test]
+    fn test_script_sig_push_only() {
+        let mut signature = der_signature(&[0x01; 32], &[0x02; 32]);
+        signature.push(0x01);
+        let mut push_only = vec![0x00, signature.len() as u8];
+        push_only.extend_from_slice(&signature);
+        push_only.extend_from_slice(&[0x51, 0x60]);
+        let push_only = Script::new(push_only);
+        assert!(push_only.is_push_only());
+        assert!(push_only.scriptsig_size_ok());
+
+        let with_op = Script::new(vec![0x01, 0xAA, 0x76]);
+        assert!(!with_op.is_push_only());
+        assert!(!Script::new(vec![0x02, 0xAA]).is_push_only());
+        assert!(!Script::new(vec![0x51; 1_651]).scriptsig_size_ok());
+
+        let input = TransactionInput::new(OutPoint::new(dummy_txid(1), 0), with_op, 0);
+        let mut tx = BitcoinTransaction::new(2, vec![input], 0);
+        tx.outputs
+            .push(TransactionOutput::new(10_000, p2wpkh_script(0x01)));
+        assert_eq!(
+            policy::check_standardness(&tx, policy::DEFAULT_DUST_RELAY_FEE),
+            Err(vec![policy::StandardnessViolation::ScriptSigNotPushOnly {
+                input_index: 0
+            }])
+        );
+    }
+
+    #[test]
+    fn test_normalized_fingerprint() {
+        let template = bip143_p2wpkh_tx();
+        let mut first = template.clone();
+        first.inputs[0].script_sig = Script::new(vec![0x01, 0xAA]);
+        first.inputs[1].witness = Witness::new(vec![vec![0x30; 71], vec![0x02; 33]]);
+        let mut second = template.clone();
+        second.inputs[0].script_sig = Script::new(vec![0x01, 0xBB]);
+        second.inputs[1].witness = Witness::new(vec![vec![0x30; 72], vec![0x02; 33]]);
+
+        assert_ne!(first.txid(), second.txid());
+        assert_eq!(
+            first.normalized_fingerprint(),
+            second.normalized_fingerprint()
+        );
+        assert_eq!(
+            first.normalized_fingerprint(),
+            template.normalized_fingerprint()
+        );
+
+        let mut other = template.clone();
+        other.outputs[0].value += 1;
+        assert_ne!(
+            other.normalized_fingerprint(),
+            template.normalized_fingerprint()
+        );
+    }
+
+    #[test]
+    fn test_addr_roundtrip() {
+        let mut ipv4 = [0u8; 16];
+        ipv4[10..].copy_from_slice(&[0xFF, 0xFF, 10, 0, 0, 1]);
+        let msg = p2p::AddrMessage {
+            addresses: vec![
+                (1_700_000_000, p2p::NetAddress::new(1, ipv4, 8333)),
+                (1_700_000_600, p2p::NetAddress::new(9, [0x20; 16], 18333)),
+            ],
+        };
+        let bytes = msg.to_bytes();
+        assert_eq!(bytes.len(), 1 + 2 * 30);
+        assert_eq!(p2p::AddrMessage::from_bytes(&bytes), Ok((msg, bytes.len())));
+        assert_eq!(
+            p2p::AddrMessage::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(BitcoinError::InsufficientBytes)
+        );
+    }
+
+    #[test]
+    fn test_conflicts() {
+        let tx = bip143_p2wpkh_tx();
+        let mut replacement = tx.clone();
+        replacement.outputs[0].value -= 1_000;
+        let mut independent = tx.clone();
+        for input in &mut independent.inputs {
+            input.previous_output.vout += 10;
+        }
+
+        assert_eq!(
+            tx.spent_outpoints().collect::<Vec<_>>(),
+            vec![&tx.inputs[0].previous_output, &tx.inputs[1].previous_output]
+        );
+        assert!(policy::conflicts(&tx, &replacement));
+        assert!(!policy::conflicts(&tx, &independent));
+        assert!(!policy::conflicts(&tx, &tx));
+    }
+
+    #[test]
+    fn test_summary() {
+        let tx = BitcoinTransaction::from_bytes(&hex::decode(BLOCK_170_TX).unwrap())
+            .unwrap()
+            .0;
+        assert_eq!(
+            tx.summary(),
+            format!("{BLOCK_170_TXID} v1 in=1 out=2 275vB")
+        );
+    }
+
+    #[test]
+    fn test_witness_program() {
+        assert_eq!(
+            p2wpkh_script(0x01).witness_program(),
+            Some((0, vec![0x01; 20]))
+        );
+        let p2wsh = Script::new([&[0x00, 0x20][..], &[0x02; 32]].concat());
+        assert_eq!(p2wsh.witness_program(), Some((0, vec![0x02; 32])));
+        let p2tr = Script::new([&[0x51, 0x20][..], &[0x03; 32]].concat());
+        assert_eq!(p2tr.witness_program(), Some((1, vec![0x03; 32])));
+
+        assert_eq!(Script::new(vec![0x51, 0x01, 0xAA]).witness_program(), None);
+        assert_eq!(p2pkh_script(0x01).witness_program(), None);
+    }
+
+    #[test]
+    fn test_input_count_compact_size_thresholds() {
+        let input = TransactionInput::new(OutPoint::new(dummy_txid(1), 0), Script::new(vec![]), 0);
+        let unbounded = ParseConfig {
+            max_tx_size: usize::MAX,
+            ..ParseConfig::default()
+        };
+        for (count, prefix) in [
+            (252, vec![0xFC]),
+            (253, vec![0xFD, 0xFD, 0x00]),
+            (65_535, vec![0xFD, 0xFF, 0xFF]),
+            (65_536, vec![0xFE, 0x00, 0x00, 0x01, 0x00]),
+        ] {
+            let mut tx = BitcoinTransaction::new(1, vec![input.clone(); count], 0);
+            tx.outputs
+                .push(TransactionOutput::new(1_000, p2wpkh_script(0x01)));
+            let bytes = tx.to_bytes();
+            assert_eq!(&bytes[4..4 + prefix.len()], &prefix[..]);
+            assert_eq!(bytes.len(), tx.serialized_len());
+
+            let (parsed, consumed) =
+                BitcoinTransaction::from_bytes_with_config(&bytes, &unbounded).unwrap();
+            assert_eq!(consumed, bytes.len());
+            assert_eq!(parsed.inputs.len(), count);
+            assert_eq!(parsed, tx);
+        }
+    }
+
+    #[test]
+    fn test_builder_enable_rbf() {
+        let tx = TransactionBuilder::new()
+            .enable_rbf(true)
+            .add_input(OutPoint::new(dummy_txid(1), 0))
+            .add_input_with_sequence(OutPoint::new(dummy_txid(2), 0), 144)
+            .add_output(Amount::from_sat(10_000), p2wpkh_script(0x01))
+            .build();
+        assert_eq!(tx.inputs[0].sequence, SEQUENCE_ENABLE_RBF);
+        assert_eq!(tx.inputs[1].sequence, 144);
+        assert!(tx.inputs[0].signals_rbf());
+        assert_eq!(
+            tx.sequence_fingerprint().rbf,
+            policy::RbfPolicy::Replaceable
+        );
+
+        let final_tx = TransactionBuilder::new()
+            .add_input(OutPoint::new(dummy_txid(1), 0))
+            .build();
+        assert!(!final_tx.inputs[0].signals_rbf());
+    }
 }